@@ -52,7 +52,9 @@ impl From<io::Error> for ProtocolError {
 #[derive(PartialEq, Debug)]
 pub enum HandshakeError {
     GetMethodRequired,
+    ConnectMethodRequired,
     NoWebsocketUpgrade,
+    NoWebsocketProtocol,
     NoConnectionUpgrade,
     NoVersionHeader,
     UnsupportedVersion,
@@ -63,7 +65,9 @@ impl fmt::Display for HandshakeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Self::GetMethodRequired => write!(f, "Only get method is allowed."),
+            Self::ConnectMethodRequired => write!(f, "Only connect method is allowed."),
             Self::NoWebsocketUpgrade => write!(f, "Upgrade header is not set to websocket."),
+            Self::NoWebsocketProtocol => write!(f, "Protocol pseudo header is not set to websocket."),
             Self::NoConnectionUpgrade => write!(f, "Connection header is not set to upgrade."),
             Self::NoVersionHeader => write!(f, " WebSocket version header is not set."),
             Self::UnsupportedVersion => write!(f, "Unsupported WebSocket version."),
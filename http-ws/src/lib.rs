@@ -96,6 +96,10 @@ impl From<HandshakeError> for Builder {
                 .status(StatusCode::METHOD_NOT_ALLOWED)
                 .header(header::ALLOW, "GET"),
 
+            HandshakeError::ConnectMethodRequired => Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .header(header::ALLOW, "CONNECT"),
+
             _ => Response::builder().status(StatusCode::BAD_REQUEST),
         }
     }
@@ -180,6 +184,41 @@ fn verify_handshake<'a>(method: &'a Method, headers: &'a HeaderMap) -> Result<&'
     Ok(value.as_bytes())
 }
 
+/// Verify WebSocket handshake request over Http/2 and Http/3 and create handshake response.
+///
+/// Http/2 carries WebSocket with extended CONNECT method defined by [RFC 8441](https://www.rfc-editor.org/rfc/rfc8441).
+/// Http/3 uses the same method defined by [RFC 9220](https://www.rfc-editor.org/rfc/rfc9220).
+/// `protocol` is the value of `:protocol` pseudo header of request. (With `h2` or `h3` crate it
+/// can be obtained from `h2::ext::Protocol` or `h3::ext::Protocol` in request extensions.)
+pub fn handshake_h2(method: &Method, headers: &HeaderMap, protocol: Option<&str>) -> Result<Builder, HandshakeError> {
+    verify_handshake_h2(method, headers, protocol)?;
+    Ok(Response::builder().status(StatusCode::OK))
+}
+
+/// Verify WebSocket handshake request over Http/2 and Http/3.
+fn verify_handshake_h2(method: &Method, headers: &HeaderMap, protocol: Option<&str>) -> Result<(), HandshakeError> {
+    // WebSocket over Http/2 accepts only CONNECT
+    if method != Method::CONNECT {
+        return Err(HandshakeError::ConnectMethodRequired);
+    }
+
+    // Check for ":protocol" pseudo header
+    if !protocol.map(|p| p.eq_ignore_ascii_case("websocket")).unwrap_or(false) {
+        return Err(HandshakeError::NoWebsocketProtocol);
+    }
+
+    // check supported version. Sec-WebSocket-Key is not used by Http/2.
+    let value = headers
+        .get(header::SEC_WEBSOCKET_VERSION)
+        .ok_or(HandshakeError::NoVersionHeader)?;
+
+    if value != "13" {
+        return Err(HandshakeError::UnsupportedVersion);
+    }
+
+    Ok(())
+}
+
 /// Create WebSocket handshake response.
 ///
 /// This function returns handshake `http::response::Builder`, ready to send to peer.
@@ -212,12 +251,26 @@ pub type WsOutput<B> = (
 
 #[cfg(feature = "stream")]
 /// A shortcut for generating a set of response types with given [Request](http::Request).
+///
+/// With `http2` or `http3` feature enabled Http/2 or Http/3 request is handshaked with [handshake_h2].
 pub fn ws<B, T, E>(req: http::Request<B>) -> Result<WsOutput<B>, HandshakeError>
 where
     B: futures_core::Stream<Item = Result<T, E>>,
     T: AsRef<[u8]>,
 {
-    let builder = handshake(req.method(), req.headers())?;
+    let builder = match req.version() {
+        #[cfg(feature = "http2")]
+        http::Version::HTTP_2 => {
+            let protocol = req.extensions().get::<h2::ext::Protocol>().map(|p| p.as_str());
+            handshake_h2(req.method(), req.headers(), protocol)?
+        }
+        #[cfg(feature = "http3")]
+        http::Version::HTTP_3 => {
+            let protocol = req.extensions().get::<h3::ext::Protocol>().map(|p| p.as_str());
+            handshake_h2(req.method(), req.headers(), protocol)?
+        }
+        _ => handshake(req.method(), req.headers())?,
+    };
 
     let body = req.into_body();
 
@@ -314,12 +367,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_handshake_h2() {
+        let req = Request::builder().method(Method::GET).body(()).unwrap();
+        assert_eq!(
+            HandshakeError::ConnectMethodRequired,
+            verify_handshake_h2(req.method(), req.headers(), Some("websocket")).unwrap_err(),
+        );
+
+        let req = Request::builder().method(Method::CONNECT).body(()).unwrap();
+        assert_eq!(
+            HandshakeError::NoWebsocketProtocol,
+            verify_handshake_h2(req.method(), req.headers(), None).unwrap_err(),
+        );
+        assert_eq!(
+            HandshakeError::NoWebsocketProtocol,
+            verify_handshake_h2(req.method(), req.headers(), Some("connect-udp")).unwrap_err(),
+        );
+        assert_eq!(
+            HandshakeError::NoVersionHeader,
+            verify_handshake_h2(req.method(), req.headers(), Some("websocket")).unwrap_err(),
+        );
+
+        let req = Request::builder()
+            .method(Method::CONNECT)
+            .header(header::SEC_WEBSOCKET_VERSION, header::HeaderValue::from_static("8"))
+            .body(())
+            .unwrap();
+        assert_eq!(
+            HandshakeError::UnsupportedVersion,
+            verify_handshake_h2(req.method(), req.headers(), Some("websocket")).unwrap_err(),
+        );
+
+        let req = Request::builder()
+            .method(Method::CONNECT)
+            .header(header::SEC_WEBSOCKET_VERSION, header::HeaderValue::from_static("13"))
+            .body(())
+            .unwrap();
+        let res = handshake_h2(req.method(), req.headers(), Some("websocket"))
+            .unwrap()
+            .body(())
+            .unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        assert!(!res.headers().contains_key(header::SEC_WEBSOCKET_ACCEPT));
+    }
+
     #[test]
     fn test_wserror_http_response() {
         let res = Builder::from(HandshakeError::GetMethodRequired).body(()).unwrap();
         assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let res = Builder::from(HandshakeError::ConnectMethodRequired).body(()).unwrap();
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers().get(header::ALLOW).unwrap(), "CONNECT");
         let res = Builder::from(HandshakeError::NoWebsocketUpgrade).body(()).unwrap();
         assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = Builder::from(HandshakeError::NoWebsocketProtocol).body(()).unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
         let res = Builder::from(HandshakeError::NoConnectionUpgrade).body(()).unwrap();
         assert_eq!(res.status(), StatusCode::BAD_REQUEST);
         let res = Builder::from(HandshakeError::NoVersionHeader).body(()).unwrap();
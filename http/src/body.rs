@@ -1,6 +1,8 @@
 use std::{
+    fmt,
     future::Future,
     pin::Pin,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 
@@ -10,6 +12,7 @@ use pin_project_lite::pin_project;
 use super::{
     bytes::{Bytes, BytesMut},
     error::BodyError,
+    http::HeaderMap,
 };
 
 /// A unified request body type for different http protocols.
@@ -21,9 +24,29 @@ pub enum RequestBody {
     H2(super::h2::RequestBody),
     #[cfg(feature = "http3")]
     H3(super::h3::RequestBody),
+    /// Body already collected in memory.
+    ///
+    /// Used by middlewares that have to read request body and pass it down to inner service.
+    ///
+    /// Note: this variant is a breaking addition to the public enum. Code matching on
+    /// [RequestBody] exhaustively has to handle it (or use a wildcard arm).
+    Bytes(Option<Bytes>),
     None,
 }
 
+impl RequestBody {
+    /// Take [OnUpgrade](crate::h1::upgrade::OnUpgrade) of Http/1 request.
+    ///
+    /// See [h1::RequestBody::on_upgrade](crate::h1::RequestBody::on_upgrade) for detail.
+    #[cfg(feature = "http1")]
+    pub fn on_upgrade(&mut self) -> Option<super::h1::upgrade::OnUpgrade> {
+        match *self {
+            Self::H1(ref mut body) => body.on_upgrade(),
+            _ => None,
+        }
+    }
+}
+
 impl Default for RequestBody {
     fn default() -> Self {
         RequestBody::None
@@ -42,6 +65,7 @@ impl Stream for RequestBody {
             Self::H2(body) => Pin::new(body).poll_next(_cx),
             #[cfg(feature = "http3")]
             Self::H3(body) => Pin::new(body).poll_next(_cx),
+            Self::Bytes(bytes) => Poll::Ready(bytes.take().map(Ok)),
             Self::None => Poll::Ready(None),
         }
     }
@@ -196,3 +220,84 @@ pub enum ResponseBodySize {
     /// Will not write Content-Length header. Can be used with chunked Transfer-Encoding.
     Stream,
 }
+
+/// Trailer fields sent after response body.
+///
+/// A `Trailers` can be inserted into response extensions. It's taken after
+/// response body is sent to end. Trailers are sent with chunked Transfer-Encoding on Http/1 and
+/// trailing HEADERS frame on Http/2 and Http/3.
+///
+/// # Examples:
+/// ```rust
+/// # use xitca_http::{body::{ResponseBody, Trailers}, http::{HeaderMap, HeaderValue, Response}};
+/// let trailers = Trailers::new();
+///
+/// let mut res: Response<ResponseBody> = Response::new(ResponseBody::None);
+/// res.extensions_mut().insert(trailers.clone());
+///
+/// // set trailers before response body stream ends.
+/// let mut map = HeaderMap::new();
+/// map.insert("grpc-status", HeaderValue::from_static("0"));
+/// trailers.set(map);
+/// ```
+#[derive(Clone, Default)]
+pub struct Trailers(Arc<Mutex<Option<HeaderMap>>>);
+
+impl Trailers {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set trailer fields. Previous value is replaced.
+    pub fn set(&self, trailers: HeaderMap) {
+        *self.0.lock().unwrap() = Some(trailers);
+    }
+
+    /// Take trailer fields. Return None when there is no trailer set or it's already taken.
+    pub fn take(&self) -> Option<HeaderMap> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+impl fmt::Debug for Trailers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Trailers").field(&*self.0.lock().unwrap()).finish()
+    }
+}
+
+/// Trailer fields received after request body.
+///
+/// For request with a body that can carry trailers a `RequestTrailers` is inserted into request
+/// extensions. It's filled when request body is read to end.
+///
+/// It's a different type from [Trailers] so request trailers are not sent back when request
+/// extensions are passed to response. (e.g. with [IntoResponse](crate::http::IntoResponse))
+#[derive(Clone, Default)]
+pub struct RequestTrailers(Trailers);
+
+impl RequestTrailers {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub(crate) fn set(&self, trailers: HeaderMap) {
+        self.0.set(trailers)
+    }
+
+    /// Take trailer fields. Return None when there is no trailer received or it's already taken.
+    #[inline]
+    pub fn take(&self) -> Option<HeaderMap> {
+        self.0.take()
+    }
+}
+
+impl fmt::Debug for RequestTrailers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RequestTrailers")
+            .field(&*(self.0).0.lock().unwrap())
+            .finish()
+    }
+}
@@ -278,7 +278,7 @@ where
 
     FA: ServiceFactory<TcpStream, Config = ()>,
     FA::Service: 'static,
-    FA::Response: AsyncIo + AsVersion,
+    FA::Response: AsyncIo + AsVersion + 'static,
 
     HttpServiceError<F::Error>: From<FA::Error>,
     F::Error: From<FE::Error>,
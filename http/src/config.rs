@@ -24,6 +24,11 @@ pub struct HttpServiceConfig<const HEADER_LIMIT: usize, const READ_BUF_LIMIT: us
     pub(crate) first_request_timeout: Duration,
     pub(crate) tls_accept_timeout: Duration,
     pub(crate) peek_protocol: bool,
+    pub(crate) h2c_upgrade: bool,
+    // only services owning io can hand it over to h1::upgrade::OnUpgrade.
+    pub(crate) io_takeover: bool,
+    pub(crate) proxy_protocol: bool,
+    pub(crate) proxy_protocol_timeout: Duration,
 }
 
 impl Default for HttpServiceConfig<DEFAULT_HEADER_LIMIT, DEFAULT_READ_BUF_LIMIT, DEFAULT_WRITE_BUF_LIMIT> {
@@ -40,6 +45,10 @@ impl HttpServiceConfig<DEFAULT_HEADER_LIMIT, DEFAULT_READ_BUF_LIMIT, DEFAULT_WRI
             first_request_timeout: Duration::from_secs(5),
             tls_accept_timeout: Duration::from_secs(3),
             peek_protocol: false,
+            h2c_upgrade: false,
+            io_takeover: true,
+            proxy_protocol: false,
+            proxy_protocol_timeout: Duration::from_secs(3),
         }
     }
 }
@@ -122,12 +131,45 @@ impl<const HEADER_LIMIT: usize, const READ_BUF_LIMIT: usize, const WRITE_BUF_LIM
     /// of alpn negotiation.
     ///
     /// This API is used to bypass alpn setting from tls and enable Http/2 protocol over
-    /// plain Tcp connection.
+    /// plain Tcp connection. Connections starting with Http/2 connection preface (prior knowledge)
+    /// are served as Http/2 and others fall back to the protocol negotiated by tls (or Http/1
+    /// when tls is not used).
     pub fn peek_protocol(mut self) -> Self {
         self.peek_protocol = true;
         self
     }
 
+    /// Enable Http/1.1 `Upgrade: h2c` requests to switch connection to plain text Http/2.
+    ///
+    /// The upgrade request is answered as the first stream of Http/2 connection. Requests with
+    /// body are served with Http/1.1 without upgrade. This only takes effect with `HttpService`
+    /// and should only be enabled for listeners without tls.
+    #[cfg(feature = "http2")]
+    pub fn enable_h2c_upgrade(mut self) -> Self {
+        self.h2c_upgrade = true;
+        self
+    }
+
+    /// Expect HAProxy PROXY protocol header (version 1 or 2) at the start of every Tcp and Unix
+    /// connection.
+    ///
+    /// Peer and local addresses of connection are overwritten with the ones reported by the header.
+    /// Connection without a valid header would be closed. Only enable it for listeners that are
+    /// exclusively reachable through proxies.
+    pub fn enable_proxy_protocol(mut self) -> Self {
+        self.proxy_protocol = true;
+        self
+    }
+
+    /// Define duration of how long a connection must finish sending it's PROXY protocol header.
+    /// (If PROXY protocol is enabled)
+    ///
+    /// Connection too slow to send the header after this duration would be closed.
+    pub fn proxy_protocol_timeout(mut self, dur: Duration) -> Self {
+        self.proxy_protocol_timeout = dur;
+        self
+    }
+
     #[doc(hidden)]
     /// A shortcut for mutating const generic params.
     pub fn mutate_const_generic<
@@ -143,6 +185,10 @@ impl<const HEADER_LIMIT: usize, const READ_BUF_LIMIT: usize, const WRITE_BUF_LIM
             first_request_timeout: self.first_request_timeout,
             tls_accept_timeout: self.tls_accept_timeout,
             peek_protocol: self.peek_protocol,
+            h2c_upgrade: self.h2c_upgrade,
+            io_takeover: self.io_takeover,
+            proxy_protocol: self.proxy_protocol,
+            proxy_protocol_timeout: self.proxy_protocol_timeout,
         }
     }
 }
@@ -0,0 +1,82 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use xitca_io::net::Stream;
+
+use crate::proxy_protocol::{ProxyHeader, ProxyTlv};
+
+/// Address information of the connection a request is received from.
+///
+/// It's inserted into every request's extensions by http dispatchers. Addresses are taken from
+/// IO type's [PeerAddr](xitca_io::net::PeerAddr) implementation and are not available for Unix domain socket
+/// connections.
+///
+/// When PROXY protocol is enabled addresses are the ones reported by the proxy.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    peer_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    tls: bool,
+    proxy_tlvs: Option<Arc<[ProxyTlv]>>,
+}
+
+impl ConnectionInfo {
+    pub fn new(peer_addr: Option<SocketAddr>, local_addr: Option<SocketAddr>) -> Self {
+        Self {
+            peer_addr,
+            local_addr,
+            tls: false,
+            proxy_tlvs: None,
+        }
+    }
+
+    // mark connection as secured with tls.
+    pub(crate) fn tls(mut self, tls: bool) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    // overwrite addresses with the ones from PROXY protocol header.
+    // LOCAL command and unknown address family keep the addresses of connection.
+    pub(crate) fn proxied(mut self, header: ProxyHeader) -> Self {
+        if let (Some(source), Some(destination)) = (header.source, header.destination) {
+            self.peer_addr = Some(source);
+            self.local_addr = Some(destination);
+        }
+
+        if !header.tlvs.is_empty() {
+            self.proxy_tlvs = Some(header.tlvs.into());
+        }
+
+        self
+    }
+
+    /// Get remote address of connection.
+    #[inline]
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Get local address of connection.
+    #[inline]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Return true when connection is secured with tls.
+    #[inline]
+    pub fn is_tls(&self) -> bool {
+        self.tls
+    }
+
+    /// Get TLV fields of PROXY protocol version 2 header.
+    #[inline]
+    pub fn proxy_tlvs(&self) -> &[ProxyTlv] {
+        self.proxy_tlvs.as_deref().unwrap_or(&[])
+    }
+}
+
+impl From<&Stream> for ConnectionInfo {
+    fn from(stream: &Stream) -> Self {
+        Self::new(stream.peer_addr(), stream.local_addr())
+    }
+}
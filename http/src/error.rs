@@ -18,6 +18,7 @@ pub enum HttpServiceError<E> {
     UnSupportedVersion(Version),
     Body(BodyError),
     Tls(TlsError),
+    ProxyProtocol(io::Error),
     #[cfg(feature = "http1")]
     H1(super::h1::Error<E>),
     // Http/2 error happen in HttpService handle.
@@ -38,6 +39,7 @@ impl<E: Debug> Debug for HttpServiceError<E> {
             Self::UnSupportedVersion(ref protocol) => write!(f, "Protocol: {:?} is not supported", protocol),
             Self::Body(ref e) => write!(f, "{:?}", e),
             Self::Tls(ref e) => write!(f, "{:?}", e),
+            Self::ProxyProtocol(ref e) => write!(f, "{:?}", e),
             #[cfg(feature = "http1")]
             Self::H1(ref e) => write!(f, "{:?}", e),
             #[cfg(feature = "http2")]
@@ -59,6 +61,8 @@ impl<E: Debug> HttpServiceError<E> {
 #[derive(Debug)]
 pub enum TimeoutError {
     TlsAccept,
+    ProxyProtocol,
+    PeekProtocol,
     #[cfg(feature = "http2")]
     H2Handshake,
 }
@@ -11,6 +11,8 @@ use futures_core::Stream;
 
 use crate::{bytes::Bytes, error::BodyError, util::futures::poll_fn};
 
+use super::upgrade::OnUpgrade;
+
 /// max buffer size 32k
 pub(crate) const MAX_BUFFER_SIZE: usize = 32_768;
 
@@ -22,7 +24,7 @@ pub(crate) const MAX_BUFFER_SIZE: usize = 32_768;
 ///
 /// Payload stream can be used as `Response` body stream.
 #[derive(Debug)]
-pub struct RequestBody(Rc<RefCell<Inner>>);
+pub struct RequestBody(Rc<RefCell<Inner>>, Option<OnUpgrade>);
 
 impl RequestBody {
     /// Create payload stream.
@@ -36,12 +38,25 @@ impl RequestBody {
     pub(super) fn create(eof: bool) -> (RequestBodySender, Self) {
         let shared = Rc::new(RefCell::new(Inner::new(eof)));
 
-        (RequestBodySender(shared.clone()), Self(shared))
+        (RequestBodySender(shared.clone()), Self(shared, None))
     }
 
     /// Create empty payload
     pub(super) fn empty() -> Self {
-        Self(Rc::new(RefCell::new(Inner::new(true))))
+        Self(Rc::new(RefCell::new(Inner::new(true))), None)
+    }
+
+    pub(super) fn set_on_upgrade(&mut self, on_upgrade: OnUpgrade) {
+        self.1 = Some(on_upgrade);
+    }
+
+    /// Take [OnUpgrade] of request. Only available when request asks for connection upgrade
+    /// and io takeover is enabled.
+    ///
+    /// Connection is taken over after upgrade response only when [OnUpgrade] is taken and
+    /// still alive at the time.
+    pub fn on_upgrade(&mut self) -> Option<OnUpgrade> {
+        self.1.take().map(OnUpgrade::claim)
     }
 }
 
@@ -1,7 +1,7 @@
 use std::future::Future;
 
 use futures_core::Stream;
-use xitca_io::io::AsyncIo;
+use xitca_io::{io::AsyncIo, net::PeerAddr};
 use xitca_service::ServiceFactory;
 
 use crate::{
@@ -10,6 +10,7 @@ use crate::{
     bytes::Bytes,
     error::{BodyError, HttpServiceError},
     http::{Request, Response},
+    version::AsVersion,
 };
 
 use super::{body::RequestBody, service::H1Service};
@@ -82,8 +83,8 @@ where
     E: 'static,
     BodyError: From<E>,
 
-    St: AsyncIo,
-    TlsSt: AsyncIo,
+    St: AsyncIo + PeerAddr,
+    TlsSt: AsyncIo + AsVersion,
 {
     type Response = ();
     type Error = HttpServiceError<F::Error>;
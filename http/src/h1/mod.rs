@@ -4,6 +4,7 @@ mod error;
 mod service;
 
 pub mod proto;
+pub mod upgrade;
 
 pub use self::body::RequestBody;
 pub use self::builder::H1ServiceBuilder;
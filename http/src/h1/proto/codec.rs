@@ -2,7 +2,10 @@ use std::{cmp, io, mem};
 
 use tracing::{trace, warn};
 
-use crate::bytes::{Buf, Bytes, BytesMut};
+use crate::{
+    bytes::{Buf, Bytes, BytesMut},
+    http::header::{HeaderMap, HeaderName, HeaderValue},
+};
 
 use super::{
     buf::WriteBuf,
@@ -107,6 +110,17 @@ impl ChunkedState {
         body: &mut BytesMut,
         size: &mut u64,
         buf: &mut Option<Bytes>,
+    ) -> io::Result<Option<ChunkedState>> {
+        self.step_with_trailer(body, size, buf, None)
+    }
+
+    // raw trailer fields are pushed to trailer buffer when it's provided.
+    fn step_with_trailer(
+        &self,
+        body: &mut BytesMut,
+        size: &mut u64,
+        buf: &mut Option<Bytes>,
+        trailer: Option<&mut BytesMut>,
     ) -> io::Result<Option<ChunkedState>> {
         use self::ChunkedState::*;
         match *self {
@@ -117,9 +131,9 @@ impl ChunkedState {
             Body => ChunkedState::read_body(body, size, buf),
             BodyCr => ChunkedState::read_body_cr(body),
             BodyLf => ChunkedState::read_body_lf(body),
-            Trailer => ChunkedState::read_trailer(body),
-            TrailerLf => ChunkedState::read_trailer_lf(body),
-            EndCr => ChunkedState::read_end_cr(body),
+            Trailer => ChunkedState::read_trailer(body, trailer),
+            TrailerLf => ChunkedState::read_trailer_lf(body, trailer),
+            EndCr => ChunkedState::read_end_cr(body, trailer),
             EndLf => ChunkedState::read_end_lf(body),
             End => Ok(Some(ChunkedState::End)),
         }
@@ -235,24 +249,32 @@ impl ChunkedState {
         }
     }
 
-    fn read_trailer(rdr: &mut BytesMut) -> io::Result<Option<ChunkedState>> {
+    fn read_trailer(rdr: &mut BytesMut, trailer: Option<&mut BytesMut>) -> io::Result<Option<ChunkedState>> {
         trace!(target: "h1_decode", "read_trailer");
-        match byte!(rdr) {
+        let b = byte!(rdr);
+        push_trailer(trailer, b)?;
+        match b {
             b'\r' => Ok(Some(ChunkedState::TrailerLf)),
             _ => Ok(Some(ChunkedState::Trailer)),
         }
     }
-    fn read_trailer_lf(rdr: &mut BytesMut) -> io::Result<Option<ChunkedState>> {
+    fn read_trailer_lf(rdr: &mut BytesMut, trailer: Option<&mut BytesMut>) -> io::Result<Option<ChunkedState>> {
         match byte!(rdr) {
-            b'\n' => Ok(Some(ChunkedState::EndCr)),
+            b'\n' => {
+                push_trailer(trailer, b'\n')?;
+                Ok(Some(ChunkedState::EndCr))
+            }
             _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid trailer end LF")),
         }
     }
 
-    fn read_end_cr(rdr: &mut BytesMut) -> io::Result<Option<ChunkedState>> {
+    fn read_end_cr(rdr: &mut BytesMut, trailer: Option<&mut BytesMut>) -> io::Result<Option<ChunkedState>> {
         match byte!(rdr) {
             b'\r' => Ok(Some(ChunkedState::EndLf)),
-            _ => Ok(Some(ChunkedState::Trailer)),
+            b => {
+                push_trailer(trailer, b)?;
+                Ok(Some(ChunkedState::Trailer))
+            }
         }
     }
 
@@ -315,10 +337,39 @@ impl TransferCoding {
     where
         W: WriteBuf,
     {
-        match *self {
-            Self::Eof | Self::Upgrade | Self::Length(0) => {}
-            Self::EncodeChunked => buf.write_static(b"0\r\n\r\n"),
-            Self::Length(n) => unreachable!("UnexpectedEof for Length Body with {} remaining", n),
+        self.encode_eof_with_trailers(None, buf)
+    }
+
+    /// Encode eof with optional trailer fields.
+    ///
+    /// Trailers can only be sent with chunked encoding. They are dropped for other encoders.
+    pub fn encode_eof_with_trailers<W>(&mut self, trailers: Option<HeaderMap>, buf: &mut W)
+    where
+        W: WriteBuf,
+    {
+        match (&*self, trailers) {
+            (Self::EncodeChunked, Some(trailers)) if !trailers.is_empty() => {
+                let mut bytes = BytesMut::from(&b"0\r\n"[..]);
+                for (name, value) in trailers.iter() {
+                    let name = name.as_str().as_bytes();
+                    let value = value.as_bytes();
+
+                    bytes.reserve(name.len() + value.len() + 4);
+                    bytes.extend_from_slice(name);
+                    bytes.extend_from_slice(b": ");
+                    bytes.extend_from_slice(value);
+                    bytes.extend_from_slice(b"\r\n");
+                }
+                bytes.extend_from_slice(b"\r\n");
+                buf.write_buf(bytes.freeze());
+            }
+            (Self::EncodeChunked, _) => buf.write_static(b"0\r\n\r\n"),
+            (Self::Eof | Self::Upgrade | Self::Length(0), trailers) => {
+                if trailers.is_some() {
+                    warn!(target: "h1_encode", "Trailers can only be sent with chunked Transfer-Encoding");
+                }
+            }
+            (Self::Length(n), _) => unreachable!("UnexpectedEof for Length Body with {} remaining", n),
             _ => unreachable!(),
         }
     }
@@ -328,6 +379,16 @@ impl TransferCoding {
     /// When returned bytes has zero length it means the encoder should enter Eof state.
     /// (calling `Self::encode_eof`)
     pub fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Bytes>> {
+        self.decode_with_trailer(src, None)
+    }
+
+    /// Decode body and push raw trailer fields following the last chunk of a chunked body to
+    /// given trailer buffer when it's provided. See [parse_trailers] for parsing them.
+    pub(crate) fn decode_with_trailer(
+        &mut self,
+        src: &mut BytesMut,
+        mut trailer: Option<&mut BytesMut>,
+    ) -> io::Result<Option<Bytes>> {
         match *self {
             Self::Length(0) => Ok(Some(Bytes::new())),
             Self::Length(ref mut remaining) => {
@@ -349,7 +410,7 @@ impl TransferCoding {
                 loop {
                     let mut buf = None;
                     // advances the chunked state
-                    *state = match state.step(src, size, &mut buf)? {
+                    *state = match state.step_with_trailer(src, size, &mut buf, trailer.as_deref_mut())? {
                         Some(state) => state,
                         None => return Ok(None),
                     };
@@ -376,6 +437,57 @@ impl TransferCoding {
     }
 }
 
+/// max number of trailer fields.
+const MAX_TRAILERS: usize = 32;
+
+/// max size of trailer fields in bytes.
+const MAX_TRAILER_SIZE: usize = 8192;
+
+/// Parse raw trailer fields buffered by [TransferCoding::decode_with_trailer].
+///
+/// Return None when there is no trailer field. Buffer is emptied afterwards.
+pub(crate) fn parse_trailers(trailer: &mut BytesMut) -> io::Result<Option<HeaderMap>> {
+    if trailer.is_empty() {
+        return Ok(None);
+    }
+
+    let mut trailer = trailer.split();
+    trailer.extend_from_slice(b"\r\n");
+
+    let mut headers = [httparse::EMPTY_HEADER; MAX_TRAILERS];
+    let headers = match httparse::parse_headers(&trailer, &mut headers) {
+        Ok(httparse::Status::Complete((_, headers))) => headers,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid chunked trailer")),
+    };
+
+    let mut map = HeaderMap::with_capacity(headers.len());
+    for header in headers {
+        let name = HeaderName::from_bytes(header.name.as_bytes())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid chunked trailer name"))?;
+        let value = HeaderValue::from_bytes(header.value)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid chunked trailer value"))?;
+        map.append(name, value);
+    }
+
+    Ok(Some(map))
+}
+
+fn push_trailer(trailer: Option<&mut BytesMut>, b: u8) -> io::Result<()> {
+    let trailer = match trailer {
+        Some(trailer) => trailer,
+        None => return Ok(()),
+    };
+
+    if trailer.len() == MAX_TRAILER_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "chunked trailer is too large",
+        ));
+    }
+    trailer.extend_from_slice(&[b]);
+    Ok(())
+}
+
 #[cold]
 #[inline(never)]
 fn incomplete_body() -> io::Error {
@@ -524,6 +636,44 @@ mod test {
         assert_eq!(0, buf.len());
     }
 
+    #[test]
+    fn test_read_chunked_trailer() {
+        let mock_buf = &mut BytesMut::from("3\r\nfoo\r\n0\r\ngrpc-status: 0\r\nx-foo: bar\r\n\r\n");
+        let mut decoder = TransferCoding::decode_chunked();
+        let mut trailer = BytesMut::new();
+
+        let buf = decoder
+            .decode_with_trailer(mock_buf, Some(&mut trailer))
+            .unwrap()
+            .unwrap();
+        assert_eq!(buf.as_ref(), b"foo");
+        assert!(parse_trailers(&mut trailer).unwrap().is_none());
+
+        let buf = decoder
+            .decode_with_trailer(mock_buf, Some(&mut trailer))
+            .unwrap()
+            .unwrap();
+        assert!(buf.is_empty());
+
+        let trailers = parse_trailers(&mut trailer).unwrap().unwrap();
+        assert_eq!(trailers.len(), 2);
+        assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+        assert_eq!(trailers.get("x-foo").unwrap(), "bar");
+
+        // trailers can only be parsed once.
+        assert!(parse_trailers(&mut trailer).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_chunked_trailer_too_large() {
+        let trailer = format!("x-foo: {}\r\n\r\n", "a".repeat(MAX_TRAILER_SIZE));
+        let mock_buf = &mut BytesMut::from(format!("0\r\n{}", trailer).as_str());
+        let e = TransferCoding::decode_chunked()
+            .decode_with_trailer(mock_buf, Some(&mut BytesMut::new()))
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
     // TODO: Revive async tests.
     // // perform an async read using a custom buffer size and causing a blocking
     // // read at the specified byte
@@ -608,6 +758,26 @@ mod test {
         assert_eq!(&***dst, b"7\r\nfoo bar\r\nD\r\nbaz quux herp\r\n0\r\n\r\n");
     }
 
+    #[test]
+    fn encode_chunked_trailers() {
+        let mut encoder = TransferCoding::encode_chunked();
+        let dst = &mut FlatBuf::<1024>::default();
+
+        encoder.encode(Bytes::from("foo"), dst);
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", HeaderValue::from_static("0"));
+        encoder.encode_eof_with_trailers(Some(trailers), dst);
+
+        assert_eq!(&***dst, b"3\r\nfoo\r\n0\r\ngrpc-status: 0\r\n\r\n");
+
+        // empty trailers encode as plain eof.
+        let mut encoder = TransferCoding::encode_chunked();
+        let dst = &mut FlatBuf::<1024>::default();
+        encoder.encode_eof_with_trailers(Some(HeaderMap::new()), dst);
+        assert_eq!(&***dst, b"0\r\n\r\n");
+    }
+
     #[test]
     fn encode_length() {
         let max_len = 8;
@@ -27,6 +27,9 @@ impl ContextState {
     /// Enable when current request is CONNECT method.
     const CONNECT: u8 = 0b_0010;
 
+    /// Enable when current request is HEAD method.
+    const HEAD: u8 = 0b_0100;
+
     const fn new() -> Self {
         Self(0)
     }
@@ -123,6 +126,12 @@ impl<'a, D, const HEADER_LIMIT: usize> Context<'a, D, HEADER_LIMIT> {
         self.state.insert(ContextState::CONNECT)
     }
 
+    /// Set Context's state to head method received.
+    #[inline]
+    pub fn set_head_method(&mut self) {
+        self.state.insert(ContextState::HEAD)
+    }
+
     /// Set connection type to [ConnectionType::CloseForce] in case error happens.
     #[inline]
     pub fn set_force_close_on_error(&mut self) {
@@ -157,6 +166,12 @@ impl<'a, D, const HEADER_LIMIT: usize> Context<'a, D, HEADER_LIMIT> {
         self.state.contains(ContextState::CONNECT)
     }
 
+    /// Get head method state.
+    #[inline]
+    pub fn is_head_method(&self) -> bool {
+        self.state.contains(ContextState::HEAD)
+    }
+
     /// Return true if connection type is [ConnectionType::Close] or [ConnectionType::CloseForce].
     #[inline]
     pub fn is_connection_closed(&self) -> bool {
@@ -73,6 +73,11 @@ impl<D, const MAX_HEADERS: usize> Context<'_, D, MAX_HEADERS> {
                     decoder.try_set(TransferCoding::upgrade())?;
                 }
 
+                if method == Method::HEAD {
+                    // set method to context so it can pass method to response.
+                    self.set_head_method();
+                }
+
                 let mut req = Request::new(());
 
                 let extensions = self.take_extensions();
@@ -194,7 +199,7 @@ mod test {
                 );
 
                 match decoder {
-                    TransferCoding::DecodeChunked(_, _) => {}
+                    TransferCoding::DecodeChunked(..) => {}
                     _ => panic!("trasnfer coding is not decoded chunked"),
                 };
 
@@ -214,7 +219,7 @@ mod test {
                 );
 
                 match decoder {
-                    TransferCoding::DecodeChunked(_, _) => {}
+                    TransferCoding::DecodeChunked(..) => {}
                     _ => panic!("trasnfer coding is not decoded chunked"),
                 };
 
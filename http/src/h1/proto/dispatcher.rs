@@ -1,4 +1,4 @@
-use std::{future::Future, io, marker::PhantomData, pin::Pin, time::Duration};
+use std::{future::Future, io, marker::PhantomData, mem, pin::Pin, time::Duration};
 
 use futures_core::stream::Stream;
 use http::{response::Parts, Request, Response};
@@ -8,14 +8,16 @@ use xitca_io::io::{AsyncIo, AsyncWrite, Interest, Ready};
 use xitca_service::Service;
 
 use crate::{
-    body::ResponseBody,
-    bytes::Bytes,
+    body::{RequestTrailers, ResponseBody, Trailers},
+    bytes::{Bytes, BytesMut},
     config::HttpServiceConfig,
+    connection::ConnectionInfo,
     date::DateTime,
     error::BodyError,
     h1::{
         body::{RequestBody, RequestBodySender},
         error::Error,
+        upgrade::{OnUpgrade, Takeover, UpgradeSender},
     },
     response,
     util::{
@@ -27,11 +29,20 @@ use crate::{
 
 use super::{
     buf::{FlatBuf, ListBuf, WriteBuf},
-    codec::TransferCoding,
+    codec::{self, TransferCoding},
     context::{ConnectionType, Context},
     error::{Parse, ProtoError},
+    h2c::{H2cUpgrade, SWITCHING_PROTOCOLS},
 };
 
+/// Connection upgrade returned by Http/1 dispatcher. The owner of io must hand it over accordingly.
+pub(crate) enum Upgrade {
+    /// Connection is upgraded to Http/2 and must be handed to Http/2 dispatcher.
+    H2c(H2cUpgrade),
+    /// Connection is taken over by service through [crate::h1::upgrade::OnUpgrade].
+    Takeover(Takeover),
+}
+
 /// function to generic over different writer buffer types dispatcher.
 pub(crate) async fn run<
     'a,
@@ -52,7 +63,8 @@ pub(crate) async fn run<
     expect: &'a X,
     service: &'a S,
     date: &'a D,
-) -> Result<(), Error<S::Error>>
+    info: ConnectionInfo,
+) -> Result<Option<Upgrade>, Error<S::Error>>
 where
     S: Service<Request<ReqB>, Response = Response<ResponseBody<ResB>>> + 'static,
     X: Service<Request<ReqB>, Response = Request<ReqB>> + 'static,
@@ -71,21 +83,22 @@ where
 
     let res = if is_vectored {
         let write_buf = ListBuf::<_, WRITE_BUF_LIMIT>::default();
-        Dispatcher::new(io, timer, config, expect, service, date, write_buf)
+        Dispatcher::new(io, timer, config, expect, service, date, info, write_buf)
             .run()
             .await
     } else {
         let write_buf = FlatBuf::<WRITE_BUF_LIMIT>::default();
-        Dispatcher::new(io, timer, config, expect, service, date, write_buf)
+        Dispatcher::new(io, timer, config, expect, service, date, info, write_buf)
             .run()
             .await
     };
 
     match res {
-        Ok(_) | Err(Error::Closed) => Ok(()),
+        Ok(upgrade) => Ok(upgrade),
+        Err(Error::Closed) => Ok(None),
         Err(Error::KeepAliveExpire) => {
             trace!(target: "h1_dispatcher", "Connection keep-alive expired. Shutting down");
-            Ok(())
+            Ok(None)
         }
         Err(e) => Err(e),
     }
@@ -109,9 +122,13 @@ struct Dispatcher<
     io: Io<'a, St, W, S::Error, READ_BUF_LIMIT, WRITE_BUF_LIMIT>,
     timer: Pin<&'a mut KeepAlive>,
     ka_dur: Duration,
+    h2c_upgrade: bool,
+    io_takeover: bool,
+    upgrade: Option<UpgradeSender>,
     ctx: Context<'a, D, HEADER_LIMIT>,
     expect: &'a X,
     service: &'a S,
+    info: ConnectionInfo,
     _phantom: PhantomData<ReqB>,
 }
 
@@ -262,41 +279,77 @@ where
         expect: &'a X,
         service: &'a S,
         date: &'a D,
+        info: ConnectionInfo,
         write_buf: W,
     ) -> Self {
         Self {
             io: Io::new(io, write_buf),
             timer,
             ka_dur: config.keep_alive_timeout,
+            h2c_upgrade: config.h2c_upgrade,
+            io_takeover: config.io_takeover,
+            upgrade: None,
             ctx: Context::new(date),
             expect,
             service,
+            info,
             _phantom: PhantomData,
         }
     }
 
-    async fn run(mut self) -> Result<(), Error<S::Error>> {
+    async fn run(mut self) -> Result<Option<Upgrade>, Error<S::Error>> {
         loop {
             match self.ctx.ctype() {
                 ConnectionType::Init | ConnectionType::KeepAlive => {
                     self.io.read().timeout(self.timer.as_mut()).await??;
                 }
                 ConnectionType::Upgrade | ConnectionType::Close => {
-                    return self.io.shutdown().timeout(self.timer.as_mut()).await?;
+                    self.io.shutdown().timeout(self.timer.as_mut()).await??;
+                    return Ok(None);
                 }
                 ConnectionType::CloseForce => {
                     unlikely();
-                    return Ok(());
+                    return Ok(None);
                 }
             }
 
             'req: while let Some(res) = self.decode_head() {
                 match res {
                     Ok((req, mut body_handle)) => {
-                        let (parts, res_body) = self.request_handler(req, &mut body_handle).await?.into_parts();
+                        if self.h2c_upgrade {
+                            match H2cUpgrade::try_from_request(&req) {
+                                Ok(Some(upgrade)) => {
+                                    return self.switch_h2c(upgrade).await.map(|u| Some(Upgrade::H2c(u)))
+                                }
+                                Ok(None) => {}
+                                Err(_) => {
+                                    self.request_error(response::bad_request)?;
+                                    break 'req;
+                                }
+                            }
+                        }
+
+                        let (mut parts, res_body) = self.request_handler(req, &mut body_handle).await?.into_parts();
+
+                        if let Some(sender) = self.upgrade.take() {
+                            let is_connect = self.ctx.is_connect_method();
+                            if sender.is_wanted() && UpgradeSender::is_upgrade_response(parts.status, is_connect) {
+                                return self.takeover(sender, parts).await.map(|t| Some(Upgrade::Takeover(t)));
+                            }
+                        }
+
+                        let trailers = parts.extensions.remove::<Trailers>();
+
                         let encoder = &mut self.encode_head(parts, &res_body)?;
 
-                        self.response_handler(res_body, encoder, body_handle).await?;
+                        if self.ctx.is_head_method() {
+                            // response to HEAD request has no body. drop it without polling.
+                            drop(res_body);
+                            self.response_handler(ResponseBody::None, encoder, body_handle, None)
+                                .await?;
+                        } else {
+                            self.response_handler(res_body, encoder, body_handle, trailers).await?;
+                        }
 
                         if self.ctx.is_connection_closed() {
                             break 'req;
@@ -321,6 +374,27 @@ where
         }
     }
 
+    // answer upgrade request with 101 and hand over the bytes already read.
+    async fn switch_h2c(&mut self, upgrade: H2cUpgrade) -> Result<H2cUpgrade, Error<S::Error>> {
+        self.io.write_buf.write_static(SWITCHING_PROTOCOLS);
+        self.io.drain_write().await?;
+
+        let read_buf = mem::take(&mut *self.io.read_buf);
+
+        Ok(upgrade.with_read_buf(read_buf))
+    }
+
+    // write upgrade response head and hand over the bytes already read.
+    // response body is dropped as io is owned by service afterwards.
+    async fn takeover(&mut self, sender: UpgradeSender, parts: Parts) -> Result<Takeover, Error<S::Error>> {
+        self.encode_head(parts, &ResponseBody::None)?;
+        self.io.drain_write().await?;
+
+        let read_buf = mem::take(&mut *self.io.read_buf);
+
+        Ok(Takeover::new(sender, read_buf))
+    }
+
     // update timer deadline according to keep alive duration.
     fn update_timer(&mut self) {
         let now = self.ctx.date.now() + self.ka_dur;
@@ -330,9 +404,25 @@ where
     fn decode_head(&mut self) -> Option<Result<DecodedHead<ReqB>, ProtoError>> {
         match self.ctx.decode_head::<READ_BUF_LIMIT>(&mut *self.io.read_buf) {
             Ok(Some((req, decoder))) => {
-                let (body_handle, body) = RequestBodyHandle::new_pair(decoder);
+                // only chunked request body can carry trailers.
+                let trailers = matches!(decoder, TransferCoding::DecodeChunked(..)).then(RequestTrailers::new);
+
+                // OnUpgrade is passed with request body as it's not thread safe.
+                let on_upgrade = (self.io_takeover && self.ctx.ctype() == ConnectionType::Upgrade).then(|| {
+                    let (sender, on_upgrade) = UpgradeSender::new_pair();
+                    self.upgrade = Some(sender);
+                    on_upgrade
+                });
+
+                let (body_handle, body) = RequestBodyHandle::new_pair(decoder, trailers.clone(), on_upgrade);
+
+                let (mut parts, _) = req.into_parts();
+                parts.extensions.insert(self.info.clone());
+
+                if let Some(trailers) = trailers {
+                    parts.extensions.insert(trailers);
+                }
 
-                let (parts, _) = req.into_parts();
                 let req = Request::from_parts(parts, body);
 
                 Some(Ok((req, body_handle)))
@@ -403,6 +493,7 @@ where
         body: ResponseBody<ResB>,
         encoder: &mut TransferCoding,
         mut body_handle: Option<RequestBodyHandle>,
+        trailers: Option<Trailers>,
     ) -> Result<(), Error<S::Error>> {
         pin!(body);
         loop {
@@ -424,7 +515,8 @@ where
                         }
                     }
 
-                    encoder.encode_eof(&mut self.io.write_buf);
+                    let trailers = trailers.and_then(|t| t.take());
+                    encoder.encode_eof_with_trailers(trailers, &mut self.io.write_buf);
                     return Ok(());
                 }
                 SelectOutput::B(res) => res?,
@@ -505,6 +597,9 @@ type DecodedHead<ReqB> = (Request<ReqB>, Option<RequestBodyHandle>);
 struct RequestBodyHandle {
     decoder: TransferCoding,
     sender: RequestBodySender,
+    trailers: Option<RequestTrailers>,
+    // raw trailer fields of chunked body. parsed into trailers when body reaches eof.
+    trailer_buf: BytesMut,
 }
 
 enum DecodeState {
@@ -515,16 +610,31 @@ enum DecodeState {
 }
 
 impl RequestBodyHandle {
-    fn new_pair<ReqB>(decoder: TransferCoding) -> (Option<Self>, ReqB)
+    fn new_pair<ReqB>(
+        decoder: TransferCoding,
+        trailers: Option<RequestTrailers>,
+        on_upgrade: Option<OnUpgrade>,
+    ) -> (Option<Self>, ReqB)
     where
         ReqB: From<RequestBody>,
     {
         if decoder.is_eof() {
-            let body = RequestBody::empty();
+            let mut body = RequestBody::empty();
+            if let Some(on_upgrade) = on_upgrade {
+                body.set_on_upgrade(on_upgrade);
+            }
             (None, body.into())
         } else {
-            let (sender, body) = RequestBody::create(false);
-            let body_handle = RequestBodyHandle { decoder, sender };
+            let (sender, mut body) = RequestBody::create(false);
+            if let Some(on_upgrade) = on_upgrade {
+                body.set_on_upgrade(on_upgrade);
+            }
+            let body_handle = RequestBodyHandle {
+                decoder,
+                sender,
+                trailers,
+                trailer_buf: BytesMut::new(),
+            };
             (Some(body_handle), body.into())
         }
     }
@@ -533,16 +643,24 @@ impl RequestBodyHandle {
         &mut self,
         read_buf: &mut FlatBuf<READ_BUF_LIMIT>,
     ) -> io::Result<DecodeState> {
-        while let Some(bytes) = self.decoder.decode(&mut *read_buf)? {
-            if bytes.is_empty() {
-                self.sender.feed_eof();
-                return Ok(DecodeState::Eof);
-            } else {
-                self.sender.feed_data(bytes);
+        loop {
+            let trailer_buf = self.trailers.as_ref().map(|_| &mut self.trailer_buf);
+
+            match self.decoder.decode_with_trailer(&mut *read_buf, trailer_buf)? {
+                Some(bytes) if bytes.is_empty() => {
+                    // trailers must be ready before request body yields eof.
+                    if let Some(ref trailers) = self.trailers {
+                        if let Some(map) = codec::parse_trailers(&mut self.trailer_buf)? {
+                            trailers.set(map);
+                        }
+                    }
+                    self.sender.feed_eof();
+                    return Ok(DecodeState::Eof);
+                }
+                Some(bytes) => self.sender.feed_data(bytes),
+                None => return Ok(DecodeState::Continue),
             }
         }
-
-        Ok(DecodeState::Continue)
     }
 
     async fn sender_ready<D, const HEADER_LIMIT: usize>(
@@ -204,6 +204,13 @@ where
             }
         }
 
+        // response to HEAD request has no body. content-length/transfer-encoding header is kept
+        // as it describes the body of the same request with GET method. dispatcher does not poll
+        // the body of it.
+        if self.is_head_method() {
+            encoding = TransferCoding::eof();
+        }
+
         // set date header if there is not any.
         if !skip_date {
             buf.reserve(D::DATE_VALUE_LENGTH + 10);
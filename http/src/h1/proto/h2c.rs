@@ -0,0 +1,547 @@
+//! Http/1.1 `Upgrade: h2c` support. See RFC 7540 section 3.2.
+//!
+//! The upgrade request is encoded as a HEADERS frame of stream 1 and injected into the byte
+//! stream right after client's connection preface and SETTINGS frame. Http/2 dispatcher would
+//! then observe it as the first request of connection and answer it on stream 1.
+//!
+//! Settings from `HTTP2-Settings` header are prepended to the payload of client's first
+//! SETTINGS frame so they are applied before the ones client sends after the preface and
+//! acknowledged together with them.
+
+use std::{
+    cmp, io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::ready;
+use xitca_io::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{
+    bytes::{BufMut, Bytes, BytesMut},
+    http::{
+        header::{HeaderMap, HeaderName, CONNECTION, CONTENT_LENGTH, HOST, TRANSFER_ENCODING, UPGRADE},
+        Method, Request, Version,
+    },
+    version::H2_PREFACE,
+};
+
+const HTTP2_SETTINGS: &str = "http2-settings";
+
+const FRAME_HEADER_LEN: usize = 9;
+// default SETTINGS_MAX_FRAME_SIZE. client settings are not known when encoding request.
+const MAX_FRAME_SIZE: usize = 16_384;
+
+const FRAME_TYPE_HEADERS: u8 = 0x1;
+const FRAME_TYPE_SETTINGS: u8 = 0x4;
+const FLAG_END_STREAM: u8 = 0x1;
+const FLAG_ACK: u8 = 0x1;
+const FLAG_END_HEADERS: u8 = 0x4;
+
+// length of a single setting. (16 bits identifier and 32 bits value)
+const SETTING_LEN: usize = 6;
+// upper bound of decoded HTTP2-Settings header.
+const MAX_SETTINGS_LEN: usize = 1024;
+
+/// Outcome of Http/1 dispatcher when connection is upgraded to Http/2.
+pub(crate) struct H2cUpgrade {
+    settings: Bytes,
+    headers_frame: Bytes,
+    read_buf: BytesMut,
+}
+
+/// `HTTP2-Settings` header of upgrade request is not a valid SETTINGS frame payload.
+pub(crate) struct InvalidSettings;
+
+impl H2cUpgrade {
+    /// Try to construct upgrade from request. None is returned when request is not a valid h2c
+    /// upgrade request and it should be served as Http/1.
+    ///
+    /// Requests with body are not upgraded as their body would have to be buffered.
+    ///
+    /// # Errors:
+    ///
+    /// [InvalidSettings] is returned when request asks for upgrade with malformed `HTTP2-Settings`
+    /// header and it should be answered with `400 Bad Request`.
+    pub(crate) fn try_from_request<B>(req: &Request<B>) -> Result<Option<Self>, InvalidSettings> {
+        if req.version() != Version::HTTP_11 || *req.method() == Method::CONNECT {
+            return Ok(None);
+        }
+
+        let headers = req.headers();
+
+        if !tokens(headers, UPGRADE).any(|t| t.eq_ignore_ascii_case("h2c")) {
+            return Ok(None);
+        }
+
+        let (mut upgrade, mut settings) = (false, false);
+        for token in tokens(headers, CONNECTION) {
+            upgrade |= token.eq_ignore_ascii_case("upgrade");
+            settings |= token.eq_ignore_ascii_case(HTTP2_SETTINGS);
+        }
+
+        if !upgrade || !settings || headers.get_all(HTTP2_SETTINGS).iter().count() != 1 {
+            return Ok(None);
+        }
+
+        let has_body = headers.contains_key(TRANSFER_ENCODING)
+            || headers
+                .get(CONTENT_LENGTH)
+                .map(|v| v.as_bytes() != b"0")
+                .unwrap_or(false);
+
+        if has_body {
+            return Ok(None);
+        }
+
+        let settings = decode_settings(headers.get(HTTP2_SETTINGS).unwrap().as_bytes()).ok_or(InvalidSettings)?;
+
+        Ok(encode_headers_frame(req).map(|headers_frame| Self {
+            settings,
+            headers_frame,
+            read_buf: BytesMut::new(),
+        }))
+    }
+
+    /// Add bytes already read from connection by Http/1 dispatcher.
+    pub(crate) fn with_read_buf(mut self, read_buf: BytesMut) -> Self {
+        self.read_buf = read_buf;
+        self
+    }
+
+    /// Wrap io of upgraded connection so it can be handed to Http/2 dispatcher.
+    pub(crate) fn into_io<Io>(self, io: Io) -> H2cIo<Io> {
+        H2cIo {
+            io,
+            state: ReadState::Preface {
+                buf: self.read_buf,
+                settings: self.settings,
+                headers_frame: self.headers_frame,
+            },
+        }
+    }
+}
+
+/// Response of accepted h2c upgrade.
+pub(crate) const SWITCHING_PROTOCOLS: &[u8] =
+    b"HTTP/1.1 101 Switching Protocols\r\nconnection: upgrade\r\nupgrade: h2c\r\n\r\n";
+
+// comma separated tokens of all header lines with given name.
+fn tokens(headers: &HeaderMap, name: HeaderName) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+}
+
+// HTTP2-Settings is base64url encoded payload of SETTINGS frame with trailing '=' omitted.
+// None when it's not valid base64url or payload is not a sequence of settings.
+fn decode_settings(value: &[u8]) -> Option<Bytes> {
+    let value = value
+        .strip_suffix(b"==")
+        .or_else(|| value.strip_suffix(b"="))
+        .unwrap_or(value);
+
+    if value.len() % 4 == 1 || value.len() / 4 * 3 > MAX_SETTINGS_LEN {
+        return None;
+    }
+
+    let mut buf = BytesMut::with_capacity(value.len() * 3 / 4);
+
+    for chunk in value.chunks(4) {
+        let mut acc = 0u32;
+        for (i, b) in chunk.iter().enumerate() {
+            let bits = match *b {
+                b'A'..=b'Z' => b - b'A',
+                b'a'..=b'z' => b - b'a' + 26,
+                b'0'..=b'9' => b - b'0' + 52,
+                b'-' => 62,
+                b'_' => 63,
+                _ => return None,
+            };
+            acc |= (bits as u32) << (18 - 6 * i);
+        }
+
+        let bytes = acc.to_be_bytes();
+        buf.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+
+    (buf.len() % SETTING_LEN == 0).then(|| buf.freeze())
+}
+
+fn encode_headers_frame<B>(req: &Request<B>) -> Option<Bytes> {
+    let headers = req.headers();
+
+    // connection specific headers are not allowed in Http/2.
+    let hop_by_hop = tokens(headers, CONNECTION)
+        .filter_map(|t| HeaderName::from_bytes(t.as_bytes()).ok())
+        .collect::<Vec<_>>();
+
+    let authority = headers
+        .get(HOST)
+        .map(|v| v.as_bytes())
+        .or_else(|| req.uri().authority().map(|a| a.as_str().as_bytes()));
+
+    let path = match req.uri().path_and_query() {
+        Some(p) => p.as_str(),
+        None => "/",
+    };
+
+    let mut block = BytesMut::new();
+
+    encode_field(&mut block, b":method", req.method().as_str().as_bytes());
+    encode_field(&mut block, b":scheme", b"http");
+    encode_field(&mut block, b":path", path.as_bytes());
+    if let Some(authority) = authority {
+        encode_field(&mut block, b":authority", authority);
+    }
+
+    for (name, value) in headers {
+        let skip = matches!(*name, CONNECTION | UPGRADE | HOST | TRANSFER_ENCODING)
+            || name == HTTP2_SETTINGS
+            || name == "keep-alive"
+            || name == "proxy-connection"
+            || name == "te"
+            || hop_by_hop.contains(name);
+
+        if !skip {
+            encode_field(&mut block, name.as_str().as_bytes(), value.as_bytes());
+        }
+    }
+
+    if block.len() > MAX_FRAME_SIZE {
+        return None;
+    }
+
+    let mut frame = BytesMut::with_capacity(FRAME_HEADER_LEN + block.len());
+    frame.put_uint(block.len() as u64, 3);
+    frame.put_u8(FRAME_TYPE_HEADERS);
+    frame.put_u8(FLAG_END_STREAM | FLAG_END_HEADERS);
+    frame.put_u32(1);
+    frame.extend_from_slice(&block);
+
+    Some(frame.freeze())
+}
+
+// hpack literal header field without indexing with new name and no huffman encoding.
+fn encode_field(buf: &mut BytesMut, name: &[u8], value: &[u8]) {
+    buf.put_u8(0);
+    encode_str(buf, name);
+    encode_str(buf, value);
+}
+
+fn encode_str(buf: &mut BytesMut, s: &[u8]) {
+    encode_int(buf, s.len(), 7);
+    buf.extend_from_slice(s);
+}
+
+// hpack integer representation with given prefix bits. the rest bits of first byte are zero.
+fn encode_int(buf: &mut BytesMut, mut value: usize, prefix_bits: u8) {
+    let max = (1 << prefix_bits) - 1;
+
+    if value < max {
+        buf.put_u8(value as u8);
+        return;
+    }
+
+    buf.put_u8(max as u8);
+    value -= max;
+
+    while value >= 128 {
+        buf.put_u8((value % 128 + 128) as u8);
+        value /= 128;
+    }
+
+    buf.put_u8(value as u8);
+}
+
+/// Io type of upgraded connection.
+///
+/// Read is buffered until client's connection preface and the SETTINGS frame following it are
+/// received. HEADERS frame of upgrade request is inserted after them and io is passed through
+/// afterwards. Write is always passed through.
+pub(crate) struct H2cIo<Io> {
+    io: Io,
+    state: ReadState,
+}
+
+enum ReadState {
+    Preface {
+        buf: BytesMut,
+        settings: Bytes,
+        headers_frame: Bytes,
+    },
+    Buffered(Bytes),
+    PassThrough,
+}
+
+// length of preface and the first frame. None when more bytes are needed.
+fn preface_len(buf: &[u8]) -> Option<usize> {
+    let header = buf.get(H2_PREFACE.len()..H2_PREFACE.len() + FRAME_HEADER_LEN)?;
+    let len = ((header[0] as usize) << 16) | ((header[1] as usize) << 8) | header[2] as usize;
+    Some(H2_PREFACE.len() + FRAME_HEADER_LEN + len)
+}
+
+// write preface and client's first frame to buf. settings are prepended to the payload of frame
+// when it's a SETTINGS frame that is not an ACK and there is enough room for them.
+// other frames are protocol error and written as is for Http/2 dispatcher to deal with.
+fn write_preface(buf: &mut BytesMut, preface: &[u8], settings: &[u8]) {
+    let (preface, frame) = preface.split_at(H2_PREFACE.len());
+    let (header, payload) = frame.split_at(FRAME_HEADER_LEN);
+
+    buf.extend_from_slice(preface);
+
+    let is_settings = header[3] == FRAME_TYPE_SETTINGS && header[4] & FLAG_ACK == 0;
+    let len = settings.len() + payload.len();
+
+    if is_settings && len <= MAX_FRAME_SIZE {
+        buf.put_uint(len as u64, 3);
+        buf.extend_from_slice(&header[3..]);
+        buf.extend_from_slice(settings);
+        buf.extend_from_slice(payload);
+    } else {
+        buf.extend_from_slice(frame);
+    }
+}
+
+impl<Io> AsyncRead for H2cIo<Io>
+where
+    Io: AsyncRead + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            match this.state {
+                ReadState::Preface {
+                    buf: ref mut read_buf,
+                    ref settings,
+                    ref headers_frame,
+                } => {
+                    match preface_len(read_buf) {
+                        // frame larger than max frame size is a protocol error. pass it through
+                        // and let Http/2 dispatcher deal with it.
+                        Some(len) if len > H2_PREFACE.len() + FRAME_HEADER_LEN + MAX_FRAME_SIZE => {
+                            this.state = ReadState::Buffered(read_buf.split().freeze());
+                        }
+                        Some(len) if read_buf.len() >= len => {
+                            let mut bytes =
+                                BytesMut::with_capacity(read_buf.len() + settings.len() + headers_frame.len());
+                            write_preface(&mut bytes, &read_buf[..len], settings);
+                            bytes.extend_from_slice(headers_frame);
+                            bytes.extend_from_slice(&read_buf[len..]);
+                            this.state = ReadState::Buffered(bytes.freeze());
+                        }
+                        _ => {
+                            let mut chunk = [0; 4096];
+                            let mut chunk = ReadBuf::new(&mut chunk);
+                            ready!(Pin::new(&mut this.io).poll_read(cx, &mut chunk))?;
+
+                            if chunk.filled().is_empty() {
+                                // connection closed before preface is complete.
+                                this.state = ReadState::Buffered(read_buf.split().freeze());
+                            } else {
+                                read_buf.extend_from_slice(chunk.filled());
+                            }
+                        }
+                    }
+                }
+                ReadState::Buffered(ref mut bytes) => {
+                    if bytes.is_empty() {
+                        this.state = ReadState::PassThrough;
+                        continue;
+                    }
+
+                    let len = cmp::min(bytes.len(), buf.remaining());
+                    buf.put_slice(&bytes.split_to(len));
+
+                    return Poll::Ready(Ok(()));
+                }
+                ReadState::PassThrough => return Pin::new(&mut this.io).poll_read(cx, buf),
+            }
+        }
+    }
+}
+
+impl<Io> AsyncWrite for H2cIo<Io>
+where
+    Io: AsyncWrite + Unpin,
+{
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    fn request(headers: &[(&'static str, &'static str)]) -> Request<()> {
+        let mut req = Request::new(());
+        *req.uri_mut() = "/foo?bar=1".parse().unwrap();
+        for (name, value) in headers {
+            req.headers_mut().append(*name, value.parse().unwrap());
+        }
+        req
+    }
+
+    #[test]
+    fn upgrade_request() {
+        let headers = [
+            ("host", "localhost"),
+            ("connection", "Upgrade, HTTP2-Settings"),
+            ("upgrade", "h2c"),
+            ("http2-settings", "AAMAAABkAARAAAAAAAIAAAAA"),
+            ("accept", "*/*"),
+        ];
+        assert!(matches!(H2cUpgrade::try_from_request(&request(&headers)), Ok(Some(_))));
+
+        // missing HTTP2-Settings in connection header.
+        let mut invalid = headers;
+        invalid[1] = ("connection", "upgrade");
+        assert!(matches!(H2cUpgrade::try_from_request(&request(&invalid)), Ok(None)));
+
+        // upgrade to other protocol.
+        let mut invalid = headers;
+        invalid[2] = ("upgrade", "websocket");
+        assert!(matches!(H2cUpgrade::try_from_request(&request(&invalid)), Ok(None)));
+
+        // request with body.
+        let mut invalid = headers;
+        invalid[4] = ("content-length", "3");
+        assert!(matches!(H2cUpgrade::try_from_request(&request(&invalid)), Ok(None)));
+
+        // malformed HTTP2-Settings.
+        let mut invalid = headers;
+        invalid[3] = ("http2-settings", "AAMAAABk!");
+        assert!(matches!(
+            H2cUpgrade::try_from_request(&request(&invalid)),
+            Err(InvalidSettings)
+        ));
+    }
+
+    #[test]
+    fn settings() {
+        // SETTINGS_MAX_CONCURRENT_STREAMS = 100, SETTINGS_INITIAL_WINDOW_SIZE = 1073741824,
+        // SETTINGS_ENABLE_PUSH = 0
+        let settings = decode_settings(b"AAMAAABkAARAAAAAAAIAAAAA").unwrap();
+        assert_eq!(
+            &settings[..],
+            &[0, 3, 0, 0, 0, 100, 0, 4, 0x40, 0, 0, 0, 0, 2, 0, 0, 0, 0]
+        );
+
+        // padding is tolerated.
+        assert_eq!(
+            decode_settings(b"AAMAAABk").unwrap(),
+            decode_settings(b"AAMAAABk==").unwrap()
+        );
+
+        assert!(decode_settings(b"").unwrap().is_empty());
+        // not a multiple of setting length.
+        assert!(decode_settings(b"AAMAAA").is_none());
+        // standard base64 alphabet.
+        assert!(decode_settings(b"AAMAAAB+").is_none());
+    }
+
+    #[test]
+    fn hpack_int() {
+        let mut buf = BytesMut::new();
+        encode_int(&mut buf, 10, 5);
+        assert_eq!(&buf[..], &[10]);
+
+        // example from RFC 7541 C.1.2.
+        let mut buf = BytesMut::new();
+        encode_int(&mut buf, 1337, 5);
+        assert_eq!(&buf[..], &[31, 154, 10]);
+    }
+
+    #[test]
+    fn headers_frame() {
+        let req = request(&[
+            ("host", "localhost"),
+            ("connection", "Upgrade, HTTP2-Settings"),
+            ("upgrade", "h2c"),
+            ("http2-settings", "AAMAAABkAARAAAAAAAIAAAAA"),
+            ("accept", "*/*"),
+        ]);
+
+        let frame = encode_headers_frame(&req).unwrap();
+
+        let len = ((frame[0] as usize) << 16) | ((frame[1] as usize) << 8) | frame[2] as usize;
+        assert_eq!(len, frame.len() - FRAME_HEADER_LEN);
+        assert_eq!(frame[3], FRAME_TYPE_HEADERS);
+        assert_eq!(frame[4], FLAG_END_STREAM | FLAG_END_HEADERS);
+        assert_eq!(&frame[5..9], &[0, 0, 0, 1]);
+
+        let mut block = BytesMut::new();
+        encode_field(&mut block, b":method", b"GET");
+        encode_field(&mut block, b":scheme", b"http");
+        encode_field(&mut block, b":path", b"/foo?bar=1");
+        encode_field(&mut block, b":authority", b"localhost");
+        encode_field(&mut block, b"accept", b"*/*");
+        assert_eq!(&frame[FRAME_HEADER_LEN..], &block[..]);
+    }
+
+    #[tokio::test]
+    async fn inject_frame() {
+        let req = request(&[
+            ("connection", "Upgrade, HTTP2-Settings"),
+            ("upgrade", "h2c"),
+            ("http2-settings", "AAMAAABk"),
+        ]);
+
+        let upgrade = H2cUpgrade::try_from_request(&req).ok().unwrap().unwrap();
+        let headers_frame = upgrade.headers_frame.clone();
+
+        // empty SETTINGS frame.
+        let settings = [0, 0, 0, 0x4, 0, 0, 0, 0, 0];
+
+        let mut input = H2_PREFACE.to_vec();
+        input.extend_from_slice(&settings);
+        input.extend_from_slice(b"rest");
+
+        // part of preface is already read by Http/1 dispatcher.
+        let upgrade = upgrade.with_read_buf(BytesMut::from(&input[..4]));
+        let mut io = upgrade.into_io(&input[4..]);
+
+        let mut output = Vec::new();
+        io.read_to_end(&mut output).await.unwrap();
+
+        // SETTINGS frame with settings from HTTP2-Settings header.
+        let mut expected = H2_PREFACE.to_vec();
+        expected.extend_from_slice(&[0, 0, 6, 0x4, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0, 3, 0, 0, 0, 100]);
+        expected.extend_from_slice(&headers_frame);
+        expected.extend_from_slice(b"rest");
+        assert_eq!(output, expected);
+    }
+}
@@ -4,6 +4,8 @@
 mod decode;
 mod dispatcher;
 mod encode;
+#[cfg_attr(not(feature = "http2"), allow(dead_code))]
+mod h2c;
 
 pub mod buf;
 pub mod codec;
@@ -11,4 +13,4 @@ pub mod context;
 pub mod error;
 pub mod header;
 
-pub(crate) use dispatcher::run;
+pub(crate) use dispatcher::{run, Upgrade};
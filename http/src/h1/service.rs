@@ -3,15 +3,17 @@ use std::future::Future;
 use futures_core::Stream;
 use http::{Request, Response};
 use tokio::pin;
-use xitca_io::io::AsyncIo;
+use xitca_io::{io::AsyncIo, net::PeerAddr};
 use xitca_service::Service;
 
 use crate::{
     body::ResponseBody,
     bytes::Bytes,
+    connection::ConnectionInfo,
     error::{BodyError, HttpServiceError, TimeoutError},
     service::HttpService,
     util::futures::Timeout,
+    version::AsVersion,
 };
 
 use super::{body::RequestBody, proto};
@@ -43,8 +45,8 @@ where
     E: 'static,
     BodyError: From<E>,
 
-    St: AsyncIo,
-    TlsSt: AsyncIo,
+    St: AsyncIo + PeerAddr,
+    TlsSt: AsyncIo + AsVersion,
 {
     type Response = ();
     type Error = HttpServiceError<S::Error>;
@@ -58,6 +60,8 @@ where
 
     fn call(&self, io: St) -> Self::Future<'_> {
         async move {
+            let info = ConnectionInfo::new(io.peer_addr(), io.local_addr());
+
             // tls accept timer.
             let timer = self.keep_alive();
             pin!(timer);
@@ -69,18 +73,27 @@ where
                 .await
                 .map_err(|_| HttpServiceError::Timeout(TimeoutError::TlsAccept))??;
 
+            let info = info.tls(io.is_tls());
+
             // update timer to first request timeout.
             self.update_first_request_deadline(timer.as_mut());
 
+            // h2c upgrade and io takeover can only be handled by HttpService.
+            let mut config = self.config;
+            config.h2c_upgrade = false;
+            config.io_takeover = false;
+
             proto::run(
                 &mut io,
                 timer.as_mut(),
-                self.config,
+                config,
                 &self.expect,
                 &self.service,
                 self.date.get(),
+                info,
             )
             .await
+            .map(|_| ())
             .map_err(Into::into)
         }
     }
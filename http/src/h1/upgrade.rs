@@ -0,0 +1,300 @@
+//! Take over io of Http/1 connection after upgrade.
+//!
+//! Requests with `Connection: upgrade` header or `CONNECT` method carry an [OnUpgrade] in their
+//! body. When service takes it and responds with `101 Switching Protocols` (or a 2xx response to
+//! `CONNECT`) while [OnUpgrade] is still alive, the dispatcher writes response head, stops
+//! handling the connection and resolves [OnUpgrade] with the io of connection.
+//!
+//! # Examples:
+//! ```rust
+//! # use xitca_http::{http::{Request, Response, StatusCode}, body::{RequestBody, ResponseBody}};
+//! # use std::convert::Infallible;
+//! async fn handler(mut req: Request<RequestBody>) -> Result<Response<ResponseBody>, Infallible> {
+//!     if let Some(on_upgrade) = req.body_mut().on_upgrade() {
+//!         // OnUpgrade resolves after response is sent. it must be awaited in another task.
+//!         tokio::task::spawn_local(async move {
+//!             if let Ok(upgraded) = on_upgrade.await {
+//!                 // upgraded implements AsyncRead and AsyncWrite.
+//!             }
+//!         });
+//!     }
+//!
+//!     let mut res = Response::new(ResponseBody::None);
+//!     *res.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+//!     Ok(res)
+//! }
+//! ```
+
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use xitca_io::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{
+    bytes::{Buf, Bytes, BytesMut},
+    http::StatusCode,
+};
+
+/// Io type that can be taken over by [OnUpgrade].
+pub trait UpgradeIo: AsyncRead + AsyncWrite + Unpin {}
+
+impl<T> UpgradeIo for T where T: AsyncRead + AsyncWrite + Unpin {}
+
+/// Io of upgraded connection.
+///
+/// Bytes received after the upgrade request but not consumed by Http/1 dispatcher are yielded
+/// by read before the io is polled.
+pub struct Upgraded {
+    io: Box<dyn UpgradeIo>,
+    read_buf: Bytes,
+}
+
+impl Upgraded {
+    /// Split into raw io and the bytes already read from it.
+    pub fn into_parts(self) -> (Box<dyn UpgradeIo>, Bytes) {
+        (self.io, self.read_buf)
+    }
+}
+
+impl fmt::Debug for Upgraded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upgraded")
+            .field("read_buf", &self.read_buf.len())
+            .finish()
+    }
+}
+
+impl AsyncRead for Upgraded {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.read_buf.has_remaining() {
+            let len = std::cmp::min(this.read_buf.len(), buf.remaining());
+            buf.put_slice(&this.read_buf.split_to(len));
+            return Poll::Ready(Ok(()));
+        }
+
+        Pin::new(&mut *this.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Upgraded {
+    #[inline]
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.get_mut().io).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.get_mut().io).poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.get_mut().io).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+/// Error type when connection is not upgraded.
+///
+/// It happens when service does not respond with an upgrade response or the connection is
+/// closed before response is sent.
+pub struct UpgradeError;
+
+impl fmt::Debug for UpgradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Connection is not upgraded")
+    }
+}
+
+impl fmt::Display for UpgradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl std::error::Error for UpgradeError {}
+
+/// A future resolves to [Upgraded] io after upgrade response is sent.
+///
+/// It's not thread safe and must be awaited on the thread the connection is handled.
+pub struct OnUpgrade(Rc<RefCell<Inner>>);
+
+impl OnUpgrade {
+    // mark as taken by service. io is only taken over for claimed OnUpgrade so request body
+    // moved elsewhere (e.g. a websocket task streaming it) would not trigger takeover.
+    pub(super) fn claim(self) -> Self {
+        self.0.borrow_mut().claimed = true;
+        self
+    }
+}
+
+impl fmt::Debug for OnUpgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnUpgrade").finish()
+    }
+}
+
+impl Future for OnUpgrade {
+    type Output = Result<Upgraded, UpgradeError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.0.borrow_mut();
+
+        if let Some(upgraded) = inner.upgraded.take() {
+            Poll::Ready(Ok(upgraded))
+        } else if inner.closed {
+            Poll::Ready(Err(UpgradeError))
+        } else {
+            inner.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+struct Inner {
+    upgraded: Option<Upgraded>,
+    claimed: bool,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/// Sender half of [OnUpgrade] held by Http/1 dispatcher.
+pub(crate) struct UpgradeSender(Rc<RefCell<Inner>>);
+
+impl UpgradeSender {
+    pub(crate) fn new_pair() -> (Self, OnUpgrade) {
+        let inner = Rc::new(RefCell::new(Inner {
+            upgraded: None,
+            claimed: false,
+            closed: false,
+            waker: None,
+        }));
+
+        (Self(inner.clone()), OnUpgrade(inner))
+    }
+
+    /// Check if [OnUpgrade] is claimed by service and still alive.
+    pub(crate) fn is_wanted(&self) -> bool {
+        Rc::strong_count(&self.0) > 1 && self.0.borrow().claimed
+    }
+
+    /// Check if response is an upgrade response that io should be taken over after it.
+    pub(crate) fn is_upgrade_response(status: StatusCode, is_connect: bool) -> bool {
+        status == StatusCode::SWITCHING_PROTOCOLS || (is_connect && status.is_success())
+    }
+
+    pub(crate) fn send<Io>(self, io: Io, read_buf: BytesMut)
+    where
+        Io: UpgradeIo + 'static,
+    {
+        let mut inner = self.0.borrow_mut();
+        inner.upgraded = Some(Upgraded {
+            io: Box::new(io),
+            read_buf: read_buf.freeze(),
+        });
+        if let Some(waker) = inner.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for UpgradeSender {
+    fn drop(&mut self) {
+        let mut inner = self.0.borrow_mut();
+        inner.closed = true;
+        if let Some(waker) = inner.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Connection upgrade that must be completed by the owner of io.
+pub(crate) struct Takeover {
+    sender: UpgradeSender,
+    read_buf: BytesMut,
+}
+
+impl Takeover {
+    pub(crate) fn new(sender: UpgradeSender, read_buf: BytesMut) -> Self {
+        Self { sender, read_buf }
+    }
+
+    /// Hand io to [OnUpgrade].
+    pub(crate) fn complete<Io>(self, io: Io)
+    where
+        Io: UpgradeIo + 'static,
+    {
+        self.sender.send(io, self.read_buf);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn takeover() {
+        let (sender, on_upgrade) = UpgradeSender::new_pair();
+        assert!(!sender.is_wanted());
+        let on_upgrade = on_upgrade.claim();
+        assert!(sender.is_wanted());
+
+        let (io, mut peer) = tokio::io::duplex(64);
+        Takeover::new(sender, BytesMut::from(&b"early"[..])).complete(io);
+
+        let mut upgraded = on_upgrade.await.unwrap();
+
+        peer.write_all(b" data").await.unwrap();
+        drop(peer);
+
+        let mut buf = Vec::new();
+        upgraded.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"early data");
+    }
+
+    #[tokio::test]
+    async fn not_upgraded() {
+        let (sender, on_upgrade) = UpgradeSender::new_pair();
+        drop(sender);
+        assert!(on_upgrade.await.is_err());
+
+        let (sender, on_upgrade) = UpgradeSender::new_pair();
+        drop(on_upgrade.claim());
+        assert!(!sender.is_wanted());
+    }
+
+    #[test]
+    fn upgrade_response() {
+        assert!(UpgradeSender::is_upgrade_response(
+            StatusCode::SWITCHING_PROTOCOLS,
+            false
+        ));
+        assert!(UpgradeSender::is_upgrade_response(StatusCode::OK, true));
+        assert!(!UpgradeSender::is_upgrade_response(StatusCode::OK, false));
+        assert!(!UpgradeSender::is_upgrade_response(StatusCode::BAD_REQUEST, true));
+    }
+}
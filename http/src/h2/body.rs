@@ -3,28 +3,58 @@ use std::{
     task::{Context, Poll},
 };
 
-use futures_core::Stream;
+use futures_core::{ready, Stream};
 use h2::RecvStream;
 
-use crate::{bytes::Bytes, error::BodyError};
+use crate::{body::RequestTrailers, bytes::Bytes, error::BodyError};
 
 /// Request body type for Http/2 specifically.
-pub struct RequestBody(RecvStream);
+pub struct RequestBody {
+    stream: RecvStream,
+    trailers: Option<RequestTrailers>,
+}
+
+impl RequestBody {
+    /// Construct body that receive trailers into given [RequestTrailers] after data is read to end.
+    pub(super) fn with_trailers(stream: RecvStream, trailers: RequestTrailers) -> Self {
+        Self {
+            stream,
+            trailers: Some(trailers),
+        }
+    }
+}
 
 impl Stream for RequestBody {
     type Item = Result<Bytes, BodyError>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let stream = &mut self.get_mut().0;
+        let this = self.get_mut();
 
-        stream.poll_data(cx).map(|opt| {
-            opt.map(|res| {
-                let bytes = res?;
-                stream.flow_control().release_capacity(bytes.len())?;
+        match ready!(this.stream.poll_data(cx)) {
+            Some(res) => {
+                let res = res.and_then(|bytes| {
+                    this.stream.flow_control().release_capacity(bytes.len())?;
+                    Ok(bytes)
+                });
+                Poll::Ready(Some(res.map_err(Into::into)))
+            }
+            None => {
+                // trailers must be ready before request body yields eof.
+                if let Some(trailers) = this.trailers.as_ref() {
+                    match ready!(this.stream.poll_trailers(cx)) {
+                        Ok(Some(map)) => trailers.set(map),
+                        Ok(None) => {}
+                        Err(e) => {
+                            this.trailers = None;
+                            return Poll::Ready(Some(Err(e.into())));
+                        }
+                    }
+                    this.trailers = None;
+                }
 
-                Ok(bytes)
-            })
-        })
+                Poll::Ready(None)
+            }
+        }
     }
 }
 
@@ -36,13 +66,13 @@ impl From<RequestBody> for crate::body::RequestBody {
 
 impl From<RecvStream> for RequestBody {
     fn from(stream: RecvStream) -> Self {
-        RequestBody(stream)
+        RequestBody { stream, trailers: None }
     }
 }
 
 // Skip h2::body::RequestBody type and convert to crate level RequestBody directly
 impl From<RecvStream> for crate::body::RequestBody {
     fn from(stream: RecvStream) -> Self {
-        Self::H2(RequestBody(stream))
+        Self::H2(RequestBody::from(stream))
     }
 }
@@ -2,7 +2,7 @@ use std::{fmt, future::Future};
 
 use futures_core::Stream;
 use http::{Request, Response};
-use xitca_io::io::AsyncIo;
+use xitca_io::{io::AsyncIo, net::PeerAddr};
 use xitca_service::ServiceFactory;
 
 use crate::{
@@ -10,6 +10,7 @@ use crate::{
     builder::HttpServiceBuilder,
     bytes::Bytes,
     error::{BodyError, HttpServiceError},
+    version::AsVersion,
 };
 
 use super::{body::RequestBody, service::H2Service};
@@ -56,8 +57,8 @@ where
     E: 'static,
     BodyError: From<E>,
 
-    St: AsyncIo,
-    TlsSt: AsyncIo,
+    St: AsyncIo + PeerAddr,
+    TlsSt: AsyncIo + AsVersion,
 {
     type Response = ();
     type Error = HttpServiceError<F::Error>;
@@ -5,7 +5,7 @@ mod service;
 
 pub mod body;
 
-pub(crate) use self::proto::Dispatcher;
+pub(crate) use self::proto::{handshake, Dispatcher};
 
 pub use self::body::RequestBody;
 pub use self::builder::H2ServiceBuilder;
@@ -18,8 +18,9 @@ use xitca_io::io::{AsyncRead, AsyncWrite};
 use xitca_service::Service;
 
 use crate::{
-    body::{ResponseBody, ResponseBodySize},
+    body::{RequestTrailers, ResponseBody, ResponseBodySize, Trailers},
     bytes::Bytes,
+    connection::ConnectionInfo,
     date::{DateTime, DateTimeHandle},
     error::{BodyError, HttpServiceError},
     h2::{body::RequestBody, error::Error},
@@ -40,6 +41,7 @@ pub(crate) struct Dispatcher<'a, TlsSt, S, ReqB> {
     ka_dur: Duration,
     service: &'a S,
     date: &'a DateTimeHandle,
+    info: ConnectionInfo,
     _req_body: PhantomData<ReqB>,
 }
 
@@ -61,6 +63,7 @@ where
         ka_dur: Duration,
         service: &'a S,
         date: &'a DateTimeHandle,
+        info: ConnectionInfo,
     ) -> Self {
         Self {
             io,
@@ -68,6 +71,7 @@ where
             ka_dur,
             service,
             date,
+            info,
             _req_body: PhantomData,
         }
     }
@@ -79,6 +83,7 @@ where
             ka_dur,
             service,
             date,
+            info,
             ..
         } = self;
 
@@ -104,8 +109,17 @@ where
                 SelectOutput::A(SelectOutput::A(Some(Ok((req, tx))))) => {
                     // Convert http::Request body type to crate::h2::Body
                     // and reconstruct as HttpRequest.
-                    let (parts, body) = req.into_parts();
-                    let body = ReqB::from(RequestBody::from(body));
+                    let (mut parts, body) = req.into_parts();
+                    parts.extensions.insert(info.clone());
+                    // request ends with HEADERS frame can not carry trailers.
+                    let body = if body.is_end_stream() {
+                        RequestBody::from(body)
+                    } else {
+                        let trailers = RequestTrailers::new();
+                        parts.extensions.insert(trailers.clone());
+                        RequestBody::with_trailers(body, trailers)
+                    };
+                    let body = ReqB::from(body);
                     let req = Request::from_parts(parts, body);
 
                     queue.push(async move {
@@ -204,7 +218,8 @@ where
     BodyError: From<BE>,
 {
     // split response to header and body.
-    let (res, body) = fut.await.map_err(Error::Service)?.into_parts();
+    let (mut res, body) = fut.await.map_err(Error::Service)?.into_parts();
+    let trailers = res.extensions.remove::<Trailers>();
     let mut res = Response::from_parts(res, ());
 
     // set response version.
@@ -242,10 +257,13 @@ where
         })
         .unwrap_or(ConnectionState::KeepAlive);
 
+    // stream must stay open after body when there are trailers to send.
+    let end_stream = is_eof && trailers.is_none();
+
     // send response and body(if there is one).
-    let mut stream = tx.send_response(res, is_eof)?;
+    let mut stream = tx.send_response(res, end_stream)?;
 
-    if !is_eof {
+    if !end_stream {
         pin!(body);
 
         while let Some(res) = body.as_mut().next().await {
@@ -267,7 +285,10 @@ where
             }
         }
 
-        stream.send_data(Bytes::new(), true)?;
+        match trailers.and_then(|t| t.take()) {
+            Some(trailers) => stream.send_trailers(trailers)?,
+            None => stream.send_data(Bytes::new(), true)?,
+        }
     }
 
     Ok(state)
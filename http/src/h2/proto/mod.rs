@@ -1,3 +1,20 @@
 mod dispatcher;
 
 pub(crate) use dispatcher::Dispatcher;
+
+use ::h2::server::{Builder, Handshake};
+use xitca_io::io::{AsyncRead, AsyncWrite};
+
+use crate::bytes::Bytes;
+
+/// Start Http/2 handshake on given io.
+///
+/// `SETTINGS_ENABLE_CONNECT_PROTOCOL` is advertised so WebSocket can be bootstrapped with
+/// extended CONNECT request. ([RFC 8441](https://www.rfc-editor.org/rfc/rfc8441))
+/// The `:protocol` pseudo header is available as `h2::ext::Protocol` in request extensions.
+pub(crate) fn handshake<Io>(io: Io) -> Handshake<Io, Bytes>
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+{
+    Builder::new().enable_connect_protocol().handshake(io)
+}
@@ -3,18 +3,26 @@ use std::{fmt, future::Future};
 use futures_core::Stream;
 use http::{Request, Response};
 use tokio::pin;
-use xitca_io::io::{AsyncRead, AsyncWrite};
+use xitca_io::{
+    io::{AsyncRead, AsyncWrite},
+    net::PeerAddr,
+};
 use xitca_service::Service;
 
 use crate::{
     body::ResponseBody,
     bytes::Bytes,
+    connection::ConnectionInfo,
     error::{BodyError, HttpServiceError, TimeoutError},
     service::HttpService,
     util::futures::Timeout,
+    version::AsVersion,
 };
 
-use super::{body::RequestBody, proto::Dispatcher};
+use super::{
+    body::RequestBody,
+    proto::{handshake, Dispatcher},
+};
 
 pub type H2Service<S, A, const HEADER_LIMIT: usize, const READ_BUF_LIMIT: usize, const WRITE_BUF_LIMIT: usize> =
     HttpService<S, RequestBody, (), A, HEADER_LIMIT, READ_BUF_LIMIT, WRITE_BUF_LIMIT>;
@@ -31,8 +39,8 @@ where
     E: 'static,
     BodyError: From<E>,
 
-    St: AsyncRead + AsyncWrite + Unpin,
-    TlsSt: AsyncRead + AsyncWrite + Unpin,
+    St: AsyncRead + AsyncWrite + Unpin + PeerAddr,
+    TlsSt: AsyncRead + AsyncWrite + Unpin + AsVersion,
 
     HttpServiceError<S::Error>: From<A::Error>,
 {
@@ -55,6 +63,8 @@ where
 
     fn call(&self, io: St) -> Self::Future<'_> {
         async move {
+            let info = ConnectionInfo::new(io.peer_addr(), io.local_addr());
+
             // tls accept timer.
             let timer = self.keep_alive();
             pin!(timer);
@@ -66,10 +76,12 @@ where
                 .await
                 .map_err(|_| HttpServiceError::Timeout(TimeoutError::TlsAccept))??;
 
+            let info = info.tls(tls_stream.is_tls());
+
             // update timer to first request timeout.
             self.update_first_request_deadline(timer.as_mut());
 
-            let mut conn = ::h2::server::handshake(tls_stream)
+            let mut conn = handshake(tls_stream)
                 .timeout(timer.as_mut())
                 .await
                 .map_err(|_| HttpServiceError::Timeout(TimeoutError::H2Handshake))??;
@@ -80,6 +92,7 @@ where
                 self.config.keep_alive_timeout,
                 &self.service,
                 self.date.get(),
+                info,
             );
 
             dispatcher.run().await?;
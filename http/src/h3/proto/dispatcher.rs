@@ -1,7 +1,6 @@
-use std::{fmt, future::Future, marker::PhantomData, rc::Rc};
+use std::{fmt, future::Future, marker::PhantomData};
 
 use futures_core::Stream;
-use futures_intrusive::sync::LocalMutex;
 use h3::{
     quic::SendStream,
     server::{self, RequestStream},
@@ -10,8 +9,9 @@ use xitca_io::net::UdpStream;
 use xitca_service::Service;
 
 use crate::{
-    body::ResponseBody,
+    body::{RequestTrailers, ResponseBody, Trailers},
     bytes::Bytes,
+    connection::ConnectionInfo,
     error::{BodyError, HttpServiceError},
     h3::{body::RequestBody, error::Error},
     http::{Request, Response},
@@ -45,12 +45,16 @@ where
     }
 
     pub(crate) async fn run(self) -> Result<(), Error<S::Error>> {
+        let info = ConnectionInfo::new(Some(self.io.peer_addr()), self.io.local_addr()).tls(true);
+
         // wait for connecting.
         let conn = self.io.connecting().await?;
 
         // construct h3 connection from quinn connection.
         let conn = h3_quinn::Connection::new(conn);
-        let mut conn = server::Connection::new(conn).await?;
+        // extended CONNECT is enabled for bootstrapping WebSocket. (RFC 9220)
+        // The `:protocol` pseudo header is available as `h3::ext::Protocol` in request extensions.
+        let mut conn = server::builder().enable_extended_connect(true).build(conn).await?;
 
         let mut queue = Queue::new();
 
@@ -59,16 +63,26 @@ where
             match conn.accept().select(queue.next()).await {
                 SelectOutput::A(Ok(Some((req, stream)))) => {
                     // Reconstruct HttpRequest to attach crate body type.
-                    let (parts, _) = req.into_parts();
+                    let (mut parts, _) = req.into_parts();
+                    parts.extensions.insert(info.clone());
+
+                    let trailers = RequestTrailers::new();
+                    parts.extensions.insert(trailers.clone());
 
-                    // a hack to split read/write of request stream.
-                    // TODO: may deadlock?
-                    let stream = Rc::new(LocalMutex::new(stream, true));
-                    let sender = stream.clone();
+                    // split read/write of request stream so they can make progress independently.
+                    // this is required by long lived bidirectional streams like WebSocket.
+                    let (stream, mut receiver) = stream.split();
                     let body = async_stream::stream! {
-                        while let Some(res) = sender.lock().await.recv_data().await.transpose() {
+                        while let Some(res) = receiver.recv_data().await.transpose() {
                             yield res;
                         }
+
+                        // trailers must be ready before request body yields eof.
+                        match receiver.recv_trailers().await {
+                            Ok(Some(map)) => trailers.set(map),
+                            Ok(None) => {}
+                            Err(e) => yield Err(e),
+                        }
                     };
                     let body = ReqB::from(RequestBody(Box::pin(body)));
                     let req = Request::from_parts(parts, body);
@@ -94,26 +108,31 @@ where
     }
 }
 
-async fn h3_handler<'a, Fut, C, B, BE, E>(fut: Fut, stream: Rc<LocalMutex<RequestStream<C>>>) -> Result<(), Error<E>>
+async fn h3_handler<'a, Fut, C, B, BE, E>(fut: Fut, mut stream: RequestStream<C>) -> Result<(), Error<E>>
 where
     Fut: Future<Output = Result<Response<ResponseBody<B>>, E>> + 'a,
     C: SendStream<Bytes>,
     B: Stream<Item = Result<Bytes, BE>>,
     BodyError: From<BE>,
 {
-    let (res, body) = fut.await.map_err(Error::Service)?.into_parts();
+    let (mut res, body) = fut.await.map_err(Error::Service)?.into_parts();
+    let trailers = res.extensions.remove::<Trailers>();
     let res = Response::from_parts(res, ());
 
-    stream.lock().await.send_response(res).await?;
+    stream.send_response(res).await?;
 
     tokio::pin!(body);
 
     while let Some(res) = body.as_mut().next().await {
         let bytes = res?;
-        stream.lock().await.send_data(bytes).await?;
+        stream.send_data(bytes).await?;
+    }
+
+    if let Some(trailers) = trailers.and_then(|t| t.take()) {
+        stream.send_trailers(trailers).await?;
     }
 
-    stream.lock().await.finish().await?;
+    stream.finish().await?;
 
     Ok(())
 }
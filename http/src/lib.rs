@@ -8,7 +8,9 @@
 #![feature(generic_associated_types, type_alias_impl_trait)]
 
 mod builder;
+mod connection;
 mod expect;
+mod proxy_protocol;
 mod response;
 mod service;
 mod tls;
@@ -76,7 +78,9 @@ pub use xitca_io::bytes;
 
 pub use body::{RequestBody, ResponseBody};
 pub use builder::HttpServiceBuilder;
+pub use connection::ConnectionInfo;
 pub use error::{BodyError, HttpServiceError};
+pub use proxy_protocol::ProxyTlv;
 pub use response::ResponseError;
 pub use service::HttpService;
 
@@ -0,0 +1,379 @@
+//! HAProxy PROXY protocol header parsing.
+//!
+//! Both the text format of version 1 and the binary format of version 2 are supported.
+//! See <https://www.haproxy.org/download/2.5/doc/proxy-protocol.txt> for specification.
+
+use std::{
+    cmp,
+    future::Future,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    time::sleep,
+};
+use xitca_io::net::TcpStream;
+
+use crate::bytes::Bytes;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LEN: usize = 107;
+
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+const V2_HEAD_LEN: usize = V2_SIGNATURE.len() + 4;
+
+/// A type-length-value field carried by PROXY protocol version 2 header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyTlv {
+    kind: u8,
+    value: Bytes,
+}
+
+impl ProxyTlv {
+    /// Get type of field. e.g. `0x01` for ALPN and `0xEA` for AWS VPC endpoint id.
+    #[inline]
+    pub fn kind(&self) -> u8 {
+        self.kind
+    }
+
+    /// Get value of field.
+    #[inline]
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+}
+
+/// Decoded PROXY protocol header.
+///
+/// Addresses are None when proxy does not forward a connection (health check for example)
+/// or the address family is not an ip one.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct ProxyHeader {
+    pub(crate) source: Option<SocketAddr>,
+    pub(crate) destination: Option<SocketAddr>,
+    pub(crate) tlvs: Vec<ProxyTlv>,
+}
+
+/// Stream that can peek into received bytes without consuming them.
+pub(crate) trait Peek: AsyncRead + Unpin {
+    type PeekFuture<'f>: Future<Output = io::Result<usize>>
+    where
+        Self: 'f;
+
+    fn peek<'f>(&'f self, buf: &'f mut [u8]) -> Self::PeekFuture<'f>;
+}
+
+impl Peek for TcpStream {
+    type PeekFuture<'f> = impl Future<Output = io::Result<usize>>;
+
+    #[inline]
+    fn peek<'f>(&'f self, buf: &'f mut [u8]) -> Self::PeekFuture<'f> {
+        TcpStream::peek(self, buf)
+    }
+}
+
+#[cfg(unix)]
+impl Peek for xitca_io::net::UnixStream {
+    type PeekFuture<'f> = impl Future<Output = io::Result<usize>>;
+
+    fn peek<'f>(&'f self, buf: &'f mut [u8]) -> Self::PeekFuture<'f> {
+        use std::mem::MaybeUninit;
+
+        use socket2::SockRef;
+        use xitca_io::io::Interest;
+
+        async move {
+            // SAFETY: socket only writes initialized bytes into buffer.
+            let buf = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+
+            loop {
+                self.readable().await?;
+
+                // try_io clears readiness of stream when peek would block.
+                match self.try_io(Interest::READABLE, || SockRef::from(self).peek(buf)) {
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                    res => return res,
+                }
+            }
+        }
+    }
+}
+
+/// Version of PROXY protocol header found at the start of connection.
+#[derive(Debug, PartialEq, Eq)]
+enum Version {
+    /// text header with the length of it including the trailing CRLF.
+    V1(usize),
+    V2,
+}
+
+/// Read PROXY protocol header from the start of given stream.
+///
+/// The header is peeked into a buffer first so only bytes belong to it are consumed from the
+/// stream and everything after it is left to the following protocol stage.
+///
+/// Caller must bound the returned future with a timeout as a client can send partial header
+/// and stall.
+pub(crate) async fn read_header<Io>(io: &mut Io) -> io::Result<ProxyHeader>
+where
+    Io: Peek,
+{
+    const MAX_BACKOFF: Duration = Duration::from_millis(64);
+
+    let mut buf = [0; V1_MAX_LEN];
+    let mut backoff = Duration::from_millis(1);
+
+    let version = loop {
+        match io.peek(&mut buf).await? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => match detect(&buf[..n])? {
+                Some(version) => break version,
+                // peek returns immediately when partial header is already buffered. back off
+                // to wait for the rest of it without spinning.
+                None => {
+                    sleep(backoff).await;
+                    backoff = cmp::min(backoff * 2, MAX_BACKOFF);
+                }
+            },
+        }
+    };
+
+    match version {
+        Version::V1(len) => {
+            io.read_exact(&mut buf[..len]).await?;
+            decode_v1(&buf[V1_PREFIX.len()..len - 2])
+        }
+        Version::V2 => {
+            let mut head = [0; V2_HEAD_LEN];
+            io.read_exact(&mut head).await?;
+
+            let len = port(&head[14..]) as usize;
+            let mut payload = vec![0; len];
+            io.read_exact(&mut payload).await?;
+
+            decode_v2(head[12], head[13], Bytes::from(payload))
+        }
+    }
+}
+
+/// Detect header version from peeked bytes.
+///
+/// Return Ok(None) when more bytes are needed to make a decision.
+fn detect(buf: &[u8]) -> io::Result<Option<Version>> {
+    if buf.starts_with(&V2_SIGNATURE) {
+        Ok(Some(Version::V2))
+    } else if buf.starts_with(V1_PREFIX) {
+        match buf.windows(2).position(|window| window == b"\r\n") {
+            Some(pos) => Ok(Some(Version::V1(pos + 2))),
+            None if buf.len() >= V1_MAX_LEN => Err(invalid("PROXY protocol v1 header is too long")),
+            None => Ok(None),
+        }
+    } else if V2_SIGNATURE.starts_with(buf) || V1_PREFIX.starts_with(buf) {
+        Ok(None)
+    } else {
+        Err(invalid("PROXY protocol header is missing"))
+    }
+}
+
+fn decode_v1(line: &[u8]) -> io::Result<ProxyHeader> {
+    let line = std::str::from_utf8(line).map_err(|_| invalid("PROXY protocol v1 header is not utf-8"))?;
+
+    let mut parts = line.split(' ');
+
+    match parts.next() {
+        Some("TCP4") | Some("TCP6") => {}
+        // receiver must ignore everything after UNKNOWN.
+        Some("UNKNOWN") => return Ok(ProxyHeader::default()),
+        _ => return Err(invalid("PROXY protocol v1 protocol is not supported")),
+    }
+
+    let mut next = || {
+        parts
+            .next()
+            .ok_or_else(|| invalid("PROXY protocol v1 header is incomplete"))
+    };
+
+    let src = next()?.parse::<IpAddr>();
+    let dst = next()?.parse::<IpAddr>();
+    let src_port = next()?.parse::<u16>();
+    let dst_port = next()?.parse::<u16>();
+
+    match (src, dst, src_port, dst_port) {
+        (Ok(src), Ok(dst), Ok(src_port), Ok(dst_port)) if parts.next().is_none() => Ok(ProxyHeader {
+            source: Some(SocketAddr::new(src, src_port)),
+            destination: Some(SocketAddr::new(dst, dst_port)),
+            tlvs: Vec::new(),
+        }),
+        _ => Err(invalid("PROXY protocol v1 header is malformed")),
+    }
+}
+
+fn decode_v2(ver_cmd: u8, family: u8, mut payload: Bytes) -> io::Result<ProxyHeader> {
+    if ver_cmd >> 4 != 2 {
+        return Err(invalid("PROXY protocol v2 version is not supported"));
+    }
+
+    let local = match ver_cmd & 0x0f {
+        0 => true,
+        1 => false,
+        _ => return Err(invalid("PROXY protocol v2 command is not supported")),
+    };
+
+    // address block length of AF_INET, AF_INET6 and AF_UNIX.
+    let addr_len = match family >> 4 {
+        0 => 0,
+        1 => 12,
+        2 => 36,
+        3 => 216,
+        _ => return Err(invalid("PROXY protocol v2 address family is not supported")),
+    };
+
+    if payload.len() < addr_len {
+        return Err(invalid("PROXY protocol v2 address block is incomplete"));
+    }
+
+    let addr = payload.split_to(addr_len);
+
+    let mut header = ProxyHeader::default();
+
+    // addresses of LOCAL command must be ignored.
+    if !local {
+        match family >> 4 {
+            1 => {
+                let src = Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]);
+                let dst = Ipv4Addr::new(addr[4], addr[5], addr[6], addr[7]);
+                header.source = Some(SocketAddr::new(src.into(), port(&addr[8..10])));
+                header.destination = Some(SocketAddr::new(dst.into(), port(&addr[10..12])));
+            }
+            2 => {
+                let src = Ipv6Addr::from(<[u8; 16]>::try_from(&addr[..16]).unwrap());
+                let dst = Ipv6Addr::from(<[u8; 16]>::try_from(&addr[16..32]).unwrap());
+                header.source = Some(SocketAddr::new(src.into(), port(&addr[32..34])));
+                header.destination = Some(SocketAddr::new(dst.into(), port(&addr[34..36])));
+            }
+            _ => {}
+        }
+    }
+
+    while !payload.is_empty() {
+        if payload.len() < 3 {
+            return Err(invalid("PROXY protocol v2 TLV is incomplete"));
+        }
+
+        let kind = payload[0];
+        let len = port(&payload[1..3]) as usize;
+
+        if payload.len() < 3 + len {
+            return Err(invalid("PROXY protocol v2 TLV is incomplete"));
+        }
+
+        let mut tlv = payload.split_to(3 + len);
+        let value = tlv.split_off(3);
+        header.tlvs.push(ProxyTlv { kind, value });
+    }
+
+    Ok(header)
+}
+
+fn port(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::AsyncWriteExt;
+    use xitca_io::net::TcpListener;
+
+    use super::*;
+
+    // connection with given bytes sent from client side. client is closed after sending.
+    async fn stream(bytes: &[u8]) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        client.write_all(bytes).await.unwrap();
+        listener.accept().await.unwrap().0
+    }
+
+    async fn rest(mut io: TcpStream) -> Vec<u8> {
+        let mut buf = Vec::new();
+        io.read_to_end(&mut buf).await.unwrap();
+        buf
+    }
+
+    #[test]
+    fn detect_version() {
+        assert_eq!(detect(b"PROX").unwrap(), None);
+        assert_eq!(detect(b"PROXY TCP4 1.1.1.1").unwrap(), None);
+        assert_eq!(detect(b"PROXY UNKNOWN\r\nGET").unwrap(), Some(Version::V1(15)));
+        assert_eq!(detect(&V2_SIGNATURE[..5]).unwrap(), None);
+        assert_eq!(detect(&V2_SIGNATURE).unwrap(), Some(Version::V2));
+        assert!(detect(b"GET / HTTP/1.1\r\n").is_err());
+        assert!(detect("PROXY TCP4 ".repeat(10).as_bytes()).is_err());
+    }
+
+    #[tokio::test]
+    async fn v1() {
+        let mut io = stream(b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET / HTTP/1.1\r\n").await;
+        let header = read_header(&mut io).await.unwrap();
+        assert_eq!(header.source, Some("192.168.0.1:56324".parse().unwrap()));
+        assert_eq!(header.destination, Some("192.168.0.11:443".parse().unwrap()));
+        assert_eq!(rest(io).await, b"GET / HTTP/1.1\r\n");
+
+        let mut io = stream(b"PROXY TCP6 2001:db8::1 2001:db8::2 1 2\r\n").await;
+        let header = read_header(&mut io).await.unwrap();
+        assert_eq!(header.source, Some("[2001:db8::1]:1".parse().unwrap()));
+
+        let mut io = stream(b"PROXY UNKNOWN ffff::1 ffff::2 1 2\r\n").await;
+        assert_eq!(read_header(&mut io).await.unwrap(), ProxyHeader::default());
+
+        let mut io = stream(b"PROXY TCP4 192.168.0.1 192.168.0.11 56324\r\n").await;
+        assert!(read_header(&mut io).await.is_err());
+
+        let mut io = stream(b"GET / HTTP/1.1\r\nHost: localhost\r\n").await;
+        assert!(read_header(&mut io).await.is_err());
+
+        let long = format!("PROXY TCP4 {}\r\n", "1".repeat(V1_MAX_LEN));
+        let mut io = stream(long.as_bytes()).await;
+        assert!(read_header(&mut io).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn v2() {
+        let mut buf = V2_SIGNATURE.to_vec();
+        // PROXY command with TCP over IPv4.
+        buf.extend_from_slice(&[0x21, 0x11, 0, 12 + 7]);
+        buf.extend_from_slice(&[127, 0, 0, 1, 10, 0, 0, 1, 0x1f, 0x90, 0x01, 0xbb]);
+        // ALPN TLV.
+        buf.extend_from_slice(&[0x01, 0, 4]);
+        buf.extend_from_slice(b"h2c!");
+        buf.extend_from_slice(b"rest");
+
+        let mut io = stream(&buf).await;
+        let header = read_header(&mut io).await.unwrap();
+        assert_eq!(header.source, Some("127.0.0.1:8080".parse().unwrap()));
+        assert_eq!(header.destination, Some("10.0.0.1:443".parse().unwrap()));
+        assert_eq!(header.tlvs.len(), 1);
+        assert_eq!(header.tlvs[0].kind(), 0x01);
+        assert_eq!(header.tlvs[0].value().as_ref(), b"h2c!");
+        assert_eq!(rest(io).await, b"rest");
+
+        // LOCAL command ignores addresses.
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.extend_from_slice(&[0x20, 0x11, 0, 12]);
+        buf.extend_from_slice(&[127, 0, 0, 1, 10, 0, 0, 1, 0x1f, 0x90, 0x01, 0xbb]);
+        let header = read_header(&mut stream(&buf).await).await.unwrap();
+        assert_eq!(header, ProxyHeader::default());
+
+        // truncated TLV.
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.extend_from_slice(&[0x21, 0x00, 0, 2, 0x01, 0]);
+        assert!(read_header(&mut stream(&buf).await).await.is_err());
+    }
+}
@@ -19,6 +19,15 @@ pub trait ResponseError<Req, Res>: fmt::Debug {
     }
 
     fn response_error(&mut self, req: &mut Req) -> Res;
+
+    /// Return true when error is caused by request not matching any route of the service.
+    /// (e.g. `RouterError::MatchError`)
+    ///
+    /// Caller can pass such request to a fallback service instead of converting the error to
+    /// a response.
+    fn is_not_matched(&self) -> bool {
+        false
+    }
 }
 
 // implement ResponseError for common error types.
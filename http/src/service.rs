@@ -2,18 +2,27 @@ use std::{fmt, future::Future, marker::PhantomData, pin::Pin};
 
 use futures_core::Stream;
 use tokio::pin;
-use xitca_io::{io::AsyncIo, net::Stream as ServerStream, net::TcpStream};
+use xitca_io::{
+    io::{AsyncIo, AsyncRead},
+    net::Stream as ServerStream,
+    net::TcpStream,
+};
 use xitca_service::Service;
 
+#[cfg(feature = "http1")]
+use super::h1::proto::Upgrade;
+
 use super::{
     body::{RequestBody, ResponseBody},
     bytes::Bytes,
     config::HttpServiceConfig,
+    connection::ConnectionInfo,
     date::{DateTime, DateTimeService},
     error::{BodyError, HttpServiceError, TimeoutError},
     http::{Request, Response, Version},
+    proxy_protocol,
     util::{futures::Timeout, keep_alive::KeepAlive},
-    version::AsVersion,
+    version::{self, AsVersion},
 };
 
 /// General purpose http service
@@ -77,6 +86,71 @@ impl<S, ReqB, X, A, const HEADER_LIMIT: usize, const READ_BUF_LIMIT: usize, cons
         timer.update(deadline);
     }
 
+    /// read PROXY protocol header from io and overwrite connection info with it.
+    ///
+    /// timer is reset to `HttpServiceConfig.tls_accept_timeout` after the header is read.
+    pub(crate) async fn read_proxy_header<Io, E>(
+        &self,
+        io: &mut Io,
+        mut timer: Pin<&mut KeepAlive>,
+        info: ConnectionInfo,
+    ) -> Result<ConnectionInfo, HttpServiceError<E>>
+    where
+        Io: proxy_protocol::Peek,
+    {
+        let now = self.date.get().now();
+
+        // deadlines are reset eagerly as they can be shorter than the current one.
+        timer.as_mut().update(now + self.config.proxy_protocol_timeout);
+        timer.as_mut().reset();
+
+        let header = proxy_protocol::read_header(io)
+            .timeout(timer.as_mut())
+            .await
+            .map_err(|_| HttpServiceError::Timeout(TimeoutError::ProxyProtocol))?
+            .map_err(HttpServiceError::ProxyProtocol)?;
+
+        let now = self.date.get().now();
+        timer.as_mut().update(now + self.config.tls_accept_timeout);
+        timer.reset();
+
+        Ok(info.proxied(header))
+    }
+
+    /// perform Http/2 handshake on io and dispatch the connection.
+    #[cfg(feature = "http2")]
+    async fn dispatch_h2<Io, B, E>(
+        &self,
+        io: Io,
+        mut timer: Pin<&mut KeepAlive>,
+        info: ConnectionInfo,
+    ) -> Result<(), HttpServiceError<S::Error>>
+    where
+        S: Service<Request<RequestBody>, Response = Response<ResponseBody<B>>> + 'static,
+        S::Error: fmt::Debug,
+        B: Stream<Item = Result<Bytes, E>> + 'static,
+        E: 'static,
+        BodyError: From<E>,
+        Io: AsyncRead + xitca_io::io::AsyncWrite + Unpin,
+    {
+        let mut conn = super::h2::handshake(io)
+            .timeout(timer.as_mut())
+            .await
+            .map_err(|_| HttpServiceError::Timeout(TimeoutError::H2Handshake))??;
+
+        super::h2::Dispatcher::new(
+            &mut conn,
+            timer,
+            self.config.keep_alive_timeout,
+            &self.service,
+            self.date.get(),
+            info,
+        )
+        .run()
+        .await
+        .map_err(Into::into)
+    }
+
     /// keep alive start with timer for `HttpServiceConfig.tls_accept_timeout`.
     ///
     /// It would be re-used for all following timer operation.
@@ -95,7 +169,7 @@ where
     S: Service<Request<RequestBody>, Response = Response<ResponseBody<B>>> + 'static,
     X: Service<Request<RequestBody>, Response = Request<RequestBody>> + 'static,
     A: Service<TcpStream> + 'static,
-    A::Response: AsyncIo + AsVersion,
+    A::Response: AsyncIo + AsVersion + 'static,
 
     HttpServiceError<S::Error>: From<A::Error>,
 
@@ -121,13 +195,29 @@ where
             let timer = self.keep_alive();
             pin!(timer);
 
+            #[allow(unused_mut)]
+            let mut info = ConnectionInfo::from(&io);
+
             match io {
                 #[cfg(feature = "http3")]
                 ServerStream::Udp(io) => super::h3::Dispatcher::new(io, &self.service)
                     .run()
                     .await
                     .map_err(From::from),
-                ServerStream::Tcp(io) => {
+                ServerStream::Tcp(mut io, _) => {
+                    if self.config.proxy_protocol {
+                        info = self.read_proxy_header(&mut io, timer.as_mut(), info).await?;
+                    }
+
+                    // peek version from connection to figure out the real protocol used
+                    // regardless of AsVersion's outcome. Only plain text Http/2 with prior
+                    // knowledge can be observed here as tls handshake is not started yet.
+                    let is_h2c = self.config.peek_protocol
+                        && version::peek_h2_preface(&io)
+                            .timeout(timer.as_mut())
+                            .await
+                            .map_err(|_| HttpServiceError::Timeout(TimeoutError::PeekProtocol))?;
+
                     #[allow(unused_mut)]
                     let mut tls_stream = self
                         .tls_acceptor
@@ -136,10 +226,10 @@ where
                         .await
                         .map_err(|_| HttpServiceError::Timeout(TimeoutError::TlsAccept))??;
 
-                    let version = if self.config.peek_protocol {
-                        // peek version from connection to figure out the real protocol used
-                        // regardless of AsVersion's outcome.
-                        todo!("peek version is not implemented yet!")
+                    info = info.tls(tls_stream.is_tls());
+
+                    let version = if is_h2c {
+                        Version::HTTP_2
                     } else {
                         tls_stream.as_version()
                     };
@@ -149,40 +239,43 @@ where
 
                     match version {
                         #[cfg(feature = "http1")]
-                        Version::HTTP_11 | Version::HTTP_10 => super::h1::proto::run(
-                            &mut tls_stream,
-                            timer.as_mut(),
-                            self.config,
-                            &self.expect,
-                            &self.service,
-                            self.date.get(),
-                        )
-                        .await
-                        .map_err(From::from),
-                        #[cfg(feature = "http2")]
-                        Version::HTTP_2 => {
-                            let mut conn = ::h2::server::handshake(tls_stream)
-                                .timeout(timer.as_mut())
-                                .await
-                                .map_err(|_| HttpServiceError::Timeout(TimeoutError::H2Handshake))??;
-
-                            super::h2::Dispatcher::new(
-                                &mut conn,
+                        Version::HTTP_11 | Version::HTTP_10 => {
+                            // h2c upgrade is only for plain text connection.
+                            let mut config = self.config;
+                            config.h2c_upgrade &= !tls_stream.is_tls();
+
+                            let upgrade = super::h1::proto::run(
+                                &mut tls_stream,
                                 timer.as_mut(),
-                                self.config.keep_alive_timeout,
+                                config,
+                                &self.expect,
                                 &self.service,
                                 self.date.get(),
+                                info.clone(),
                             )
-                            .run()
-                            .await
-                            .map_err(Into::into)
+                            .await?;
+
+                            match upgrade {
+                                Some(Upgrade::Takeover(takeover)) => {
+                                    takeover.complete(tls_stream);
+                                    Ok(())
+                                }
+                                #[cfg(feature = "http2")]
+                                Some(Upgrade::H2c(upgrade)) => {
+                                    self.dispatch_h2(upgrade.into_io(tls_stream), timer.as_mut(), info)
+                                        .await
+                                }
+                                _ => Ok(()),
+                            }
                         }
+                        #[cfg(feature = "http2")]
+                        Version::HTTP_2 => self.dispatch_h2(tls_stream, timer.as_mut(), info).await,
                         version => Err(HttpServiceError::UnSupportedVersion(version)),
                     }
                 }
                 #[cfg(unix)]
                 #[allow(unused_mut)]
-                ServerStream::Unix(mut io) => {
+                ServerStream::Unix(mut io, _) => {
                     #[cfg(not(feature = "http1"))]
                     {
                         drop(io);
@@ -191,19 +284,35 @@ where
 
                     #[cfg(feature = "http1")]
                     {
+                        if self.config.proxy_protocol {
+                            info = self.read_proxy_header(&mut io, timer.as_mut(), info).await?;
+                        }
+
                         // update timer to first request timeout.
                         self.update_first_request_deadline(timer.as_mut());
 
-                        super::h1::proto::run(
+                        let upgrade = super::h1::proto::run(
                             &mut io,
                             timer.as_mut(),
                             self.config,
                             &self.expect,
                             &self.service,
                             self.date.get(),
+                            info.clone(),
                         )
-                        .await
-                        .map_err(From::from)
+                        .await?;
+
+                        match upgrade {
+                            Some(Upgrade::Takeover(takeover)) => {
+                                takeover.complete(io);
+                                Ok(())
+                            }
+                            #[cfg(feature = "http2")]
+                            Some(Upgrade::H2c(upgrade)) => {
+                                self.dispatch_h2(upgrade.into_io(io), timer.as_mut(), info).await
+                            }
+                            _ => Ok(()),
+                        }
                     }
                 }
             }
@@ -36,6 +36,11 @@ where
             .map(Self::from_alpn)
             .unwrap_or(Version::HTTP_11)
     }
+
+    #[inline]
+    fn is_tls(&self) -> bool {
+        true
+    }
 }
 
 impl<S> Deref for TlsStream<S> {
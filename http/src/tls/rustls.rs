@@ -35,6 +35,11 @@ impl<S> AsVersion for TlsStream<S> {
             .map(Self::from_alpn)
             .unwrap_or(Version::HTTP_11)
     }
+
+    #[inline]
+    fn is_tls(&self) -> bool {
+        true
+    }
 }
 
 impl<S> Deref for TlsStream<S> {
@@ -8,10 +8,10 @@ use pin_project_lite::pin_project;
 
 use super::keep_alive::{KeepAlive, KeepAliveExpired};
 
-#[cfg(any(feature = "http1", feature = "http2", feature = "http3"))]
+#[cfg(any(feature = "http1", feature = "http2", feature = "http3", feature = "grpc"))]
 pub(crate) use poll::*;
 
-#[cfg(any(feature = "http1", feature = "http2", feature = "http3"))]
+#[cfg(any(feature = "http1", feature = "http2", feature = "http3", feature = "grpc"))]
 mod poll {
     use super::*;
 
@@ -0,0 +1,573 @@
+//! In memory response cache middleware.
+//!
+//! Only `GET` and `HEAD` requests are cached. A response is stored when:
+//!
+//! - status code is `200 OK`.
+//! - body is [ResponseBody::Bytes] and not larger than [Cache::max_entry_size].
+//! - `Cache-Control` header has `max-age` or `s-maxage` directive and does not have `no-store`,
+//!   `no-cache` or `private` directive.
+//! - `Vary` header is not `*`.
+//! - `Set-Cookie` header is absent.
+//! - request has no `Authorization` header or `Cache-Control` header has `public`, `s-maxage`
+//!   or `must-revalidate` directive. (RFC 9111 section 3.5)
+//!
+//! Entries are keyed by method, host, uri and the request headers listed in response's `Vary`
+//! header. Memory usage is bounded by [Cache::new] and least recently used entries are evicted
+//! first. Concurrent identical requests are collapsed into a single call to inner service. When
+//! its response can not be stored the collapsed requests are released to call inner service.
+//!
+//! Every worker thread has its own cache.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use xitca_service::{Service, Transform};
+
+use crate::{
+    body::ResponseBody,
+    bytes::Bytes,
+    http::{
+        header::{HeaderMap, HeaderName, HeaderValue, AGE, AUTHORIZATION, CACHE_CONTROL, HOST, SET_COOKIE, VARY},
+        Method, Request, Response, StatusCode, Version,
+    },
+};
+
+/// A factory for response cache service.
+#[derive(Clone, Copy)]
+pub struct Cache {
+    max_size: usize,
+    max_entry_size: usize,
+}
+
+impl Cache {
+    /// Construct a cache with given max size in bytes for every worker thread.
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            max_entry_size: 1024 * 1024,
+        }
+    }
+
+    /// Max size in bytes of a single response body that can be cached.
+    ///
+    /// Default to 1MiB.
+    pub fn max_entry_size(mut self, size: usize) -> Self {
+        self.max_entry_size = size;
+        self
+    }
+}
+
+impl<S, ReqB, ResB> Transform<S, Request<ReqB>> for Cache
+where
+    S: Service<Request<ReqB>, Response = Response<ResponseBody<ResB>>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Transform = CacheService<S>;
+    type InitError = ();
+    type Future = impl Future<Output = Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let config = *self;
+        async move {
+            Ok(CacheService {
+                service,
+                config,
+                store: RefCell::new(Store::default()),
+                in_flight: RefCell::new(HashMap::new()),
+            })
+        }
+    }
+}
+
+// method and host + path and query of request.
+type PrimaryKey = (Method, String);
+
+fn primary_key<B>(req: &Request<B>) -> PrimaryKey {
+    // Http/1 requests carry host in Host header and the rest carry it in uri.
+    let host = match req.uri().authority() {
+        Some(authority) => authority.as_str(),
+        None => req.headers().get(HOST).and_then(|h| h.to_str().ok()).unwrap_or(""),
+    };
+    let path = req.uri().path_and_query().map_or("/", |p| p.as_str());
+
+    (req.method().clone(), format!("{}{}", host, path))
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Key {
+    primary: PrimaryKey,
+    vary: Vec<Option<HeaderValue>>,
+}
+
+struct Entry {
+    version: Version,
+    headers: HeaderMap,
+    body: Bytes,
+    created: Instant,
+    expires: Instant,
+    size: usize,
+    tick: u64,
+}
+
+#[derive(Default)]
+struct Store {
+    // header names of Vary header and count of entries for every primary key.
+    primary: HashMap<PrimaryKey, (Vec<HeaderName>, usize)>,
+    entries: HashMap<Key, Entry>,
+    // lru index ordered by last access tick.
+    lru: BTreeMap<u64, Key>,
+    tick: u64,
+    size: usize,
+}
+
+impl Store {
+    fn key(&self, primary: PrimaryKey, headers: &HeaderMap) -> Key {
+        let vary = match self.primary.get(&primary) {
+            Some((names, _)) => vary_values(names, headers),
+            None => Vec::new(),
+        };
+
+        Key { primary, vary }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, key: &Key, now: Instant) -> Option<(Version, HeaderMap, Bytes, Duration)> {
+        let (expires, old) = self.entries.get(key).map(|entry| (entry.expires, entry.tick))?;
+
+        if expires <= now {
+            self.remove(key.clone());
+            return None;
+        }
+
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(key).unwrap();
+        entry.tick = tick;
+        let res = (
+            entry.version,
+            entry.headers.clone(),
+            entry.body.clone(),
+            now - entry.created,
+        );
+
+        self.lru.remove(&old);
+        self.lru.insert(tick, key.clone());
+
+        Some(res)
+    }
+
+    // return false when entry is too large to be stored.
+    fn insert(&mut self, primary: PrimaryKey, names: Vec<HeaderName>, key: Key, entry: Entry, max_size: usize) -> bool {
+        if entry.size > max_size {
+            return false;
+        }
+
+        self.remove(key.clone());
+
+        match self.primary.get_mut(&primary) {
+            Some((n, count)) => {
+                *n = names;
+                *count += 1;
+            }
+            None => {
+                self.primary.insert(primary, (names, 1));
+            }
+        }
+
+        let tick = self.next_tick();
+        self.size += entry.size;
+        self.lru.insert(tick, key.clone());
+        self.entries.insert(key, Entry { tick, ..entry });
+
+        while self.size > max_size {
+            match self.lru.keys().next().copied() {
+                Some(tick) => {
+                    let key = self.lru.get(&tick).unwrap().clone();
+                    self.remove(key);
+                }
+                None => break,
+            }
+        }
+
+        true
+    }
+
+    fn remove(&mut self, key: Key) {
+        if let Some(entry) = self.entries.remove(&key) {
+            self.size -= entry.size;
+            self.lru.remove(&entry.tick);
+
+            if let Some((_, count)) = self.primary.get_mut(&key.primary) {
+                *count -= 1;
+                if *count == 0 {
+                    self.primary.remove(&key.primary);
+                }
+            }
+        }
+    }
+}
+
+fn vary_values(names: &[HeaderName], headers: &HeaderMap) -> Vec<Option<HeaderValue>> {
+    names.iter().map(|name| headers.get(name).cloned()).collect()
+}
+
+// parse Vary header of response. return None when response can not be cached.
+fn vary_names(headers: &HeaderMap) -> Option<Vec<HeaderName>> {
+    let mut names = Vec::new();
+    for value in headers.get_all(VARY) {
+        for name in value.to_str().ok()?.split(',') {
+            let name = name.trim();
+            if name == "*" {
+                return None;
+            }
+            if !name.is_empty() {
+                names.push(HeaderName::from_bytes(name.as_bytes()).ok()?);
+            }
+        }
+    }
+    Some(names)
+}
+
+// parse Cache-Control header of response. return None when response can not be cached.
+// authorized is whether request carries Authorization header.
+fn max_age(headers: &HeaderMap, authorized: bool) -> Option<Duration> {
+    // response setting cookie is specific to the client.
+    if headers.contains_key(SET_COOKIE) {
+        return None;
+    }
+
+    let mut max_age = None;
+    let mut s_max_age = None;
+    let mut shared = false;
+
+    for value in headers.get_all(CACHE_CONTROL) {
+        for directive in value.to_str().ok()?.split(',') {
+            let directive = directive.trim();
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive, None),
+            };
+
+            if name.eq_ignore_ascii_case("no-store")
+                || name.eq_ignore_ascii_case("no-cache")
+                || name.eq_ignore_ascii_case("private")
+            {
+                return None;
+            } else if name.eq_ignore_ascii_case("public") || name.eq_ignore_ascii_case("must-revalidate") {
+                shared = true;
+            } else if name.eq_ignore_ascii_case("max-age") {
+                max_age = value.and_then(|v| v.parse().ok());
+            } else if name.eq_ignore_ascii_case("s-maxage") {
+                s_max_age = value.and_then(|v| v.parse().ok());
+            }
+        }
+    }
+
+    // response to authorized request can only be stored when explicitly allowed.
+    if authorized && !shared && s_max_age.is_none() {
+        return None;
+    }
+
+    s_max_age.or(max_age).filter(|age| *age > 0).map(Duration::from_secs)
+}
+
+// check request's Cache-Control header for skipping cache lookup.
+fn bypass(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|d| {
+            let d = d.trim();
+            d.eq_ignore_ascii_case("no-cache") || d.eq_ignore_ascii_case("no-store")
+        })
+}
+
+#[derive(Default)]
+struct InFlight {
+    done: Cell<bool>,
+    // response is stored and can be looked up by waiters.
+    stored: Cell<bool>,
+    wakers: RefCell<Vec<Waker>>,
+}
+
+struct Wait<'a>(&'a InFlight);
+
+impl Future for Wait<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.0.done.get() {
+            Poll::Ready(())
+        } else {
+            self.0.wakers.borrow_mut().push(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+// guard for notifying collapsed requests when fetch is finished or cancelled.
+struct InFlightGuard<'a> {
+    map: &'a RefCell<HashMap<Key, Rc<InFlight>>>,
+    key: Key,
+    flight: Rc<InFlight>,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.map.borrow_mut().remove(&self.key);
+        self.flight.done.set(true);
+        for waker in self.flight.wakers.borrow_mut().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+pub struct CacheService<S> {
+    service: S,
+    config: Cache,
+    store: RefCell<Store>,
+    in_flight: RefCell<HashMap<Key, Rc<InFlight>>>,
+}
+
+impl<S> CacheService<S> {
+    fn lookup<B>(&self, key: &Key, method: &Method) -> Option<Response<ResponseBody<B>>> {
+        let (version, headers, body, age) = self.store.borrow_mut().get(key, Instant::now())?;
+
+        let mut res = Response::new(if *method == Method::HEAD {
+            ResponseBody::None
+        } else {
+            ResponseBody::Bytes { bytes: body }
+        });
+        *res.version_mut() = version;
+        *res.headers_mut() = headers;
+        res.headers_mut().insert(AGE, age.as_secs().into());
+
+        Some(res)
+    }
+}
+
+impl<S, ReqB, ResB> Service<Request<ReqB>> for CacheService<S>
+where
+    S: Service<Request<ReqB>, Response = Response<ResponseBody<ResB>>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Ready<'f>
+    where
+        S: 'f,
+    = S::Ready<'f>;
+    type Future<'f>
+    where
+        S: 'f,
+    = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        self.service.ready()
+    }
+
+    fn call(&self, req: Request<ReqB>) -> Self::Future<'_> {
+        async move {
+            let method = req.method().clone();
+
+            if !matches!(method, Method::GET | Method::HEAD) {
+                return self.service.call(req).await;
+            }
+
+            let primary = primary_key(&req);
+
+            let guard = if bypass(req.headers()) {
+                None
+            } else {
+                loop {
+                    let key = self.store.borrow().key(primary.clone(), req.headers());
+
+                    if let Some(res) = self.lookup(&key, &method) {
+                        return Ok(res);
+                    }
+
+                    let flight = self.in_flight.borrow().get(&key).cloned();
+
+                    match flight {
+                        // identical request is in flight. wait for it and lookup again when its
+                        // response is stored. when the response turns out to be uncacheable
+                        // waiters are released and call inner service by themselves.
+                        Some(flight) => {
+                            Wait(&*flight).await;
+                            if !flight.stored.get() {
+                                break None;
+                            }
+                        }
+                        None => {
+                            let flight = Rc::new(InFlight::default());
+                            self.in_flight.borrow_mut().insert(key.clone(), flight.clone());
+                            break Some(InFlightGuard {
+                                map: &self.in_flight,
+                                key,
+                                flight,
+                            });
+                        }
+                    }
+                }
+            };
+
+            let req_headers = req.headers().clone();
+            let authorized = req_headers.contains_key(AUTHORIZATION);
+
+            let mut res = self.service.call(req).await?;
+
+            if res.status() == StatusCode::OK {
+                if let (Some(ttl), Some(names), ResponseBody::Bytes { bytes }) = (
+                    max_age(res.headers(), authorized),
+                    vary_names(res.headers()),
+                    res.body(),
+                ) {
+                    if bytes.len() <= self.config.max_entry_size {
+                        let now = Instant::now();
+                        let key = Key {
+                            primary: primary.clone(),
+                            vary: vary_values(&names, &req_headers),
+                        };
+                        let entry = Entry {
+                            version: res.version(),
+                            headers: res.headers().clone(),
+                            body: bytes.clone(),
+                            created: now,
+                            expires: now + ttl,
+                            size: bytes.len() + primary.1.len(),
+                            tick: 0,
+                        };
+                        let stored = self
+                            .store
+                            .borrow_mut()
+                            .insert(primary, names, key, entry, self.config.max_size);
+
+                        if let Some(ref guard) = guard {
+                            guard.flight.stored.set(stored);
+                        }
+                    }
+                }
+
+                res.headers_mut().insert(AGE, HeaderValue::from_static("0"));
+            }
+
+            drop(guard);
+
+            Ok(res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(size: usize) -> Entry {
+        let now = Instant::now();
+        Entry {
+            version: Version::HTTP_11,
+            headers: HeaderMap::new(),
+            body: Bytes::from(vec![0; size]),
+            created: now,
+            expires: now + Duration::from_secs(60),
+            size,
+            tick: 0,
+        }
+    }
+
+    fn key(uri: &str) -> Key {
+        Key {
+            primary: (Method::GET, uri.to_string()),
+            vary: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn lru() {
+        let mut store = Store::default();
+        let now = Instant::now();
+
+        store.insert(key("/a").primary, Vec::new(), key("/a"), entry(4), 10);
+        store.insert(key("/b").primary, Vec::new(), key("/b"), entry(4), 10);
+
+        // touch /a so /b becomes least recently used.
+        assert!(store.get(&key("/a"), now).is_some());
+
+        store.insert(key("/c").primary, Vec::new(), key("/c"), entry(4), 10);
+
+        assert!(store.get(&key("/a"), now).is_some());
+        assert!(store.get(&key("/b"), now).is_none());
+        assert!(store.get(&key("/c"), now).is_some());
+        assert_eq!(store.size, 8);
+        assert!(!store.primary.contains_key(&key("/b").primary));
+
+        // expired entry is removed on lookup.
+        assert!(store.get(&key("/a"), now + Duration::from_secs(61)).is_none());
+        assert_eq!(store.size, 4);
+    }
+
+    #[test]
+    fn primary() {
+        use crate::http::Uri;
+
+        let mut req = Request::new(());
+        *req.uri_mut() = Uri::from_static("/a?b=c");
+        req.headers_mut().insert(HOST, HeaderValue::from_static("foo.com"));
+        assert_eq!(primary_key(&req), (Method::GET, String::from("foo.com/a?b=c")));
+
+        req.headers_mut().insert(HOST, HeaderValue::from_static("bar.com"));
+        assert_eq!(primary_key(&req), (Method::GET, String::from("bar.com/a?b=c")));
+
+        *req.uri_mut() = Uri::from_static("https://baz.com/a");
+        assert_eq!(primary_key(&req), (Method::GET, String::from("baz.com/a")));
+    }
+
+    #[test]
+    fn cache_control() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("public, max-age=60"));
+        assert_eq!(max_age(&headers, false), Some(Duration::from_secs(60)));
+
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("max-age=60, s-maxage=10"));
+        assert_eq!(max_age(&headers, false), Some(Duration::from_secs(10)));
+
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("private, max-age=60"));
+        assert_eq!(max_age(&headers, false), None);
+
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        assert_eq!(max_age(&headers, false), None);
+
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("max-age=60"));
+        headers.insert(SET_COOKIE, HeaderValue::from_static("session=abc"));
+        assert_eq!(max_age(&headers, false), None);
+        headers.remove(SET_COOKIE);
+
+        // response to authorized request is only stored when explicitly allowed.
+        assert_eq!(max_age(&headers, true), None);
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("public, max-age=60"));
+        assert_eq!(max_age(&headers, true), Some(Duration::from_secs(60)));
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("s-maxage=10"));
+        assert_eq!(max_age(&headers, true), Some(Duration::from_secs(10)));
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("must-revalidate, max-age=60"));
+        assert_eq!(max_age(&headers, true), Some(Duration::from_secs(60)));
+
+        headers.insert(VARY, HeaderValue::from_static("accept-encoding, accept"));
+        assert_eq!(vary_names(&headers).unwrap().len(), 2);
+
+        headers.insert(VARY, HeaderValue::from_static("*"));
+        assert!(vary_names(&headers).is_none());
+    }
+}
@@ -0,0 +1,457 @@
+//! ETag and conditional request middleware.
+//!
+//! ETag is computed for responses with [ResponseBody::Bytes] body when inner service does not
+//! set one. Conditional requests are handled according to RFC 7232:
+//!
+//! - `GET` and `HEAD` requests with matching `If-None-Match` (or not modified since
+//!   `If-Modified-Since`) are answered with `304 Not Modified`.
+//! - Unsafe requests with `If-Match` or `If-Unmodified-Since` are evaluated against the current
+//!   [Validators] of the resource given by [ETag::validators] and answered with
+//!   `412 Precondition Failed` when the precondition fails. Inner service is not called in that
+//!   case.
+//!
+//! [Preconditions] of unsafe requests are also inserted into request's extensions so a handler
+//! can evaluate them by itself when no validator lookup is configured.
+
+use std::{
+    future::{Future, Ready},
+    time::SystemTime,
+};
+
+use xitca_service::{Service, Transform};
+
+use crate::{
+    body::ResponseBody,
+    http::{
+        header::{
+            HeaderMap, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+            IF_UNMODIFIED_SINCE, LAST_MODIFIED,
+        },
+        Method, Request, Response, StatusCode,
+    },
+};
+
+/// A factory for ETag service.
+#[derive(Clone, Copy)]
+pub struct ETag<F = NoValidators> {
+    weak: bool,
+    validators: F,
+}
+
+impl Default for ETag {
+    fn default() -> Self {
+        Self::strong()
+    }
+}
+
+impl ETag {
+    /// Generate strong ETag. Strong ETag indicates responses are byte-for-byte identical.
+    pub fn strong() -> Self {
+        Self {
+            weak: false,
+            validators: NoValidators,
+        }
+    }
+
+    /// Generate weak ETag. Weak ETag indicates responses are semantically equivalent.
+    pub fn weak() -> Self {
+        Self {
+            weak: true,
+            validators: NoValidators,
+        }
+    }
+}
+
+impl<F> ETag<F> {
+    /// Look up current [Validators] of the resource an unsafe request targets.
+    ///
+    /// The function resolves to `None` when the resource does not exist. Preconditions of unsafe
+    /// requests are evaluated with the result and failed ones are answered with
+    /// `412 Precondition Failed` without calling inner service.
+    ///
+    /// # Example:
+    /// ```rust
+    /// # use xitca_http::{http::{header::HeaderValue, Request}, util::middleware::{ETag, Validators}};
+    /// let etag = ETag::strong().validators(|req: &Request<()>| {
+    ///     let path = req.uri().path().to_owned();
+    ///     async move {
+    ///         // look up the resource by path.
+    ///         (path == "/exists").then(|| Validators {
+    ///             etag: Some(HeaderValue::from_static("\"v1\"")),
+    ///             last_modified: None,
+    ///         })
+    ///     }
+    /// });
+    /// ```
+    pub fn validators<F1>(self, validators: F1) -> ETag<F1> {
+        ETag {
+            weak: self.weak,
+            validators,
+        }
+    }
+
+    fn generate(&self, bytes: &[u8]) -> HeaderValue {
+        let hash = fnv1a(bytes);
+
+        let value = if self.weak {
+            format!("W/\"{:x}-{:x}\"", bytes.len(), hash)
+        } else {
+            format!("\"{:x}-{:x}\"", bytes.len(), hash)
+        };
+
+        HeaderValue::from_str(&value).unwrap()
+    }
+
+    // set etag header to response when possible and return a copy of it.
+    fn tag<B>(&self, res: &mut Response<ResponseBody<B>>) -> Option<HeaderValue> {
+        if let Some(etag) = res.headers().get(ETAG) {
+            return Some(etag.clone());
+        }
+
+        if !res.status().is_success() {
+            return None;
+        }
+
+        let etag = match res.body() {
+            ResponseBody::Bytes { bytes } => self.generate(bytes),
+            _ => return None,
+        };
+
+        res.headers_mut().insert(ETAG, etag.clone());
+        Some(etag)
+    }
+}
+
+/// Current validators of a resource.
+#[derive(Debug, Clone, Default)]
+pub struct Validators {
+    /// Current entity tag of the resource.
+    pub etag: Option<HeaderValue>,
+    /// Last modification date of the resource.
+    pub last_modified: Option<SystemTime>,
+}
+
+/// Lookup of current [Validators] of the resource a request targets.
+///
+/// Implemented for `Fn(&Req) -> impl Future<Output = Option<Validators>>` and [NoValidators].
+pub trait ValidatorsLookup<Req> {
+    type Future: Future<Output = Option<Validators>>;
+
+    /// Return `None` when no lookup is available and preconditions are left to handler.
+    fn lookup(&self, req: &Req) -> Option<Self::Future>;
+}
+
+/// Default of [ETag] where preconditions of unsafe requests are left to handler.
+#[derive(Clone, Copy)]
+pub struct NoValidators;
+
+impl<Req> ValidatorsLookup<Req> for NoValidators {
+    type Future = Ready<Option<Validators>>;
+
+    #[inline]
+    fn lookup(&self, _: &Req) -> Option<Self::Future> {
+        None
+    }
+}
+
+impl<F, Fut, Req> ValidatorsLookup<Req> for F
+where
+    F: Fn(&Req) -> Fut,
+    Fut: Future<Output = Option<Validators>>,
+{
+    type Future = Fut;
+
+    #[inline]
+    fn lookup(&self, req: &Req) -> Option<Self::Future> {
+        Some(self(req))
+    }
+}
+
+/// Preconditions of an unsafe request. (`If-Match` and `If-Unmodified-Since` headers)
+///
+/// It's inserted into request's extensions by [ETag] middleware.
+#[derive(Debug, Clone)]
+pub struct Preconditions {
+    if_match: Option<HeaderValue>,
+    if_unmodified_since: Option<SystemTime>,
+}
+
+impl Preconditions {
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let if_match = headers.get(IF_MATCH).cloned();
+        let if_unmodified_since = headers.get(IF_UNMODIFIED_SINCE).and_then(parse_date);
+
+        (if_match.is_some() || if_unmodified_since.is_some()).then(|| Self {
+            if_match,
+            if_unmodified_since,
+        })
+    }
+
+    /// Evaluate preconditions against current state of the resource.
+    ///
+    /// `exists` is whether resource currently has a representation. `etag` and `last_modified`
+    /// are its current validators. Return false when request must be answered with
+    /// `412 Precondition Failed`.
+    pub fn check(&self, exists: bool, etag: Option<&HeaderValue>, last_modified: Option<SystemTime>) -> bool {
+        match self.if_match {
+            // If-Match takes precedence and If-Unmodified-Since is ignored when present.
+            Some(ref tags) => match etag {
+                Some(etag) => exists && match_list(tags, etag, true),
+                None => exists && tags.as_bytes() == b"*",
+            },
+            None => match (self.if_unmodified_since, last_modified) {
+                (Some(since), Some(modified)) => modified <= since,
+                // header is ignored when resource has no modification date.
+                _ => true,
+            },
+        }
+    }
+}
+
+impl<S, F, ReqB, ResB> Transform<S, Request<ReqB>> for ETag<F>
+where
+    S: Service<Request<ReqB>, Response = Response<ResponseBody<ResB>>>,
+    F: ValidatorsLookup<Request<ReqB>> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Transform = ETagService<S, F>;
+    type InitError = ();
+    type Future = impl Future<Output = Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let config = self.clone();
+        async move { Ok(ETagService { service, config }) }
+    }
+}
+
+pub struct ETagService<S, F = NoValidators> {
+    service: S,
+    config: ETag<F>,
+}
+
+impl<S, F, ReqB, ResB> Service<Request<ReqB>> for ETagService<S, F>
+where
+    S: Service<Request<ReqB>, Response = Response<ResponseBody<ResB>>>,
+    F: ValidatorsLookup<Request<ReqB>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Ready<'f>
+    where
+        S: 'f,
+    = S::Ready<'f>;
+    type Future<'f>
+    where
+        S: 'f,
+        F: 'f,
+    = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        self.service.ready()
+    }
+
+    fn call(&self, mut req: Request<ReqB>) -> Self::Future<'_> {
+        async move {
+            let is_safe = matches!(*req.method(), Method::GET | Method::HEAD);
+
+            if is_safe {
+                let if_none_match = req.headers().get(IF_NONE_MATCH).cloned();
+                let if_modified_since = req.headers().get(IF_MODIFIED_SINCE).and_then(parse_date);
+
+                let mut res = self.service.call(req).await?;
+
+                if res.status() != StatusCode::OK {
+                    return Ok(res);
+                }
+
+                let etag = self.config.tag(&mut res);
+
+                let not_modified = match (if_none_match, etag) {
+                    (Some(ref tags), Some(ref etag)) => match_list(tags, etag, false),
+                    (Some(_), None) => false,
+                    (None, _) => match (if_modified_since, last_modified(res.headers())) {
+                        (Some(since), Some(modified)) => modified <= since,
+                        _ => false,
+                    },
+                };
+
+                if not_modified {
+                    *res.status_mut() = StatusCode::NOT_MODIFIED;
+                    *res.body_mut() = ResponseBody::None;
+                    res.headers_mut().remove(CONTENT_LENGTH);
+                    res.headers_mut().remove(CONTENT_TYPE);
+                }
+
+                Ok(res)
+            } else {
+                if let Some(preconditions) = Preconditions::from_headers(req.headers()) {
+                    if let Some(current) = self.config.validators.lookup(&req) {
+                        let passed = match current.await {
+                            Some(current) => preconditions.check(true, current.etag.as_ref(), current.last_modified),
+                            None => preconditions.check(false, None, None),
+                        };
+
+                        if !passed {
+                            let mut res = Response::new(ResponseBody::None);
+                            *res.status_mut() = StatusCode::PRECONDITION_FAILED;
+                            return Ok(res);
+                        }
+                    }
+
+                    req.extensions_mut().insert(preconditions);
+                }
+
+                self.service.call(req).await
+            }
+        }
+    }
+}
+
+// FNV-1a hash. ETag must stay the same across restarts and builds with different Rust versions
+// which std's DefaultHasher does not guarantee.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn parse_date(value: &HeaderValue) -> Option<SystemTime> {
+    value.to_str().ok().and_then(|v| httpdate::parse_http_date(v).ok())
+}
+
+fn last_modified(headers: &HeaderMap) -> Option<SystemTime> {
+    headers.get(LAST_MODIFIED).and_then(parse_date)
+}
+
+// match an etag against a comma separated list of entity tags.
+// strong comparison requires both tags not being weak.
+fn match_list(list: &HeaderValue, etag: &HeaderValue, strong: bool) -> bool {
+    let list = match list.to_str() {
+        Ok(list) => list.trim(),
+        Err(_) => return false,
+    };
+
+    if list == "*" {
+        return true;
+    }
+
+    let etag = match etag.to_str() {
+        Ok(etag) => etag,
+        Err(_) => return false,
+    };
+
+    let (etag_weak, etag) = split_weak(etag);
+
+    if strong && etag_weak {
+        return false;
+    }
+
+    list.split(',').map(str::trim).any(|tag| {
+        let (weak, tag) = split_weak(tag);
+        !(strong && weak) && tag == etag
+    })
+}
+
+fn split_weak(tag: &str) -> (bool, &str) {
+    match tag.strip_prefix("W/") {
+        Some(tag) => (true, tag),
+        None => (false, tag),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn etag_match() {
+        let strong = HeaderValue::from_static("\"abc\"");
+        let weak = HeaderValue::from_static("W/\"abc\"");
+
+        let list = HeaderValue::from_static("\"foo\", W/\"abc\"");
+
+        assert!(match_list(&list, &strong, false));
+        assert!(match_list(&list, &weak, false));
+        assert!(!match_list(&list, &strong, true));
+        assert!(!match_list(&list, &weak, true));
+
+        let list = HeaderValue::from_static("\"abc\"");
+        assert!(match_list(&list, &strong, true));
+
+        let list = HeaderValue::from_static("*");
+        assert!(match_list(&list, &weak, true));
+    }
+
+    #[test]
+    fn preconditions() {
+        let mut headers = HeaderMap::new();
+        assert!(Preconditions::from_headers(&headers).is_none());
+
+        let etag = HeaderValue::from_static("\"abc\"");
+        let modified = httpdate::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+
+        headers.insert(
+            IF_UNMODIFIED_SINCE,
+            HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"),
+        );
+        let pre = Preconditions::from_headers(&headers).unwrap();
+        assert!(pre.check(true, None, Some(modified)));
+        assert!(!pre.check(true, None, Some(modified + std::time::Duration::from_secs(1))));
+        // ignored when resource has no modification date.
+        assert!(pre.check(true, None, None));
+
+        headers.insert(IF_MATCH, HeaderValue::from_static("\"abc\""));
+        let pre = Preconditions::from_headers(&headers).unwrap();
+        assert!(pre.check(true, Some(&etag), None));
+        assert!(!pre.check(true, Some(&HeaderValue::from_static("\"def\"")), None));
+        assert!(!pre.check(false, None, None));
+
+        headers.insert(IF_MATCH, HeaderValue::from_static("*"));
+        let pre = Preconditions::from_headers(&headers).unwrap();
+        assert!(pre.check(true, None, None));
+        assert!(!pre.check(false, None, None));
+    }
+
+    #[test]
+    fn generate() {
+        let etag = ETag::strong().generate(b"hello");
+        assert_eq!(etag, "\"5-a430d84680aabd0b\"");
+
+        let etag = ETag::weak().generate(b"hello");
+        assert_eq!(etag, "W/\"5-a430d84680aabd0b\"");
+    }
+
+    #[tokio::test]
+    async fn precondition_failed() {
+        use std::convert::Infallible;
+
+        use xitca_service::{fn_service, ServiceFactory, ServiceFactoryExt};
+
+        async fn handler(_: Request<()>) -> Result<Response<ResponseBody>, Infallible> {
+            Ok(Response::new(ResponseBody::None))
+        }
+
+        let etag = ETag::strong().validators(|_: &Request<()>| async {
+            Some(Validators {
+                etag: Some(HeaderValue::from_static("\"abc\"")),
+                last_modified: None,
+            })
+        });
+
+        let service = fn_service(handler).transform(etag).new_service(()).await.ok().unwrap();
+
+        let mut req = Request::new(());
+        *req.method_mut() = Method::PUT;
+        req.headers_mut().insert(IF_MATCH, HeaderValue::from_static("\"def\""));
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+        let mut req = Request::new(());
+        *req.method_mut() = Method::PUT;
+        req.headers_mut().insert(IF_MATCH, HeaderValue::from_static("\"abc\""));
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,482 @@
+//! Trusted proxy middleware resolving client information from forwarding headers.
+//!
+//! RFC 7239 `Forwarded` header takes precedence over `X-Forwarded-For`, `X-Forwarded-Proto` and
+//! `X-Forwarded-Host` when both are present. Forwarding headers are only trusted when the peer of
+//! connection is one of the configured proxies. Hops are walked from the closest one and the walk
+//! stops at the first address that is not a trusted proxy.
+
+use std::{
+    error, fmt,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+};
+
+use xitca_service::{Service, Transform};
+
+use crate::{
+    connection::ConnectionInfo,
+    http::{
+        header::{AsHeaderName, HeaderMap, FORWARDED, HOST},
+        Request,
+    },
+};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+const X_FORWARDED_HOST: &str = "x-forwarded-host";
+
+/// An ip network in CIDR notation. e.g. `10.0.0.0/8` or `fd00::/8`.
+///
+/// A plain ip address is parsed as a network containing only itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Construct a network from address and prefix length.
+    ///
+    /// Bits of address beyond prefix length are ignored.
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, CidrError> {
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        if prefix > max {
+            return Err(CidrError::Prefix(prefix));
+        }
+
+        Ok(Self { addr, prefix })
+    }
+
+    /// Check if given ip address is in the network.
+    ///
+    /// Ipv4 mapped ipv6 addresses are matched against ipv4 networks.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = CidrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((addr, prefix)) => {
+                let addr = addr.parse().map_err(|_| CidrError::Addr(addr.to_string()))?;
+                let prefix = prefix.parse().map_err(|_| CidrError::Addr(s.to_string()))?;
+                Self::new(addr, prefix)
+            }
+            None => {
+                let addr = s.parse().map_err(|_| CidrError::Addr(s.to_string()))?;
+                let prefix = if matches!(addr, IpAddr::V4(_)) { 32 } else { 128 };
+                Self::new(addr, prefix)
+            }
+        }
+    }
+}
+
+/// Error type for constructing [Cidr].
+pub enum CidrError {
+    /// Address part can not be parsed.
+    Addr(String),
+    /// Prefix length is too long for the address family.
+    Prefix(u8),
+}
+
+impl fmt::Debug for CidrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Addr(ref addr) => write!(f, "Invalid network address: {}", addr),
+            Self::Prefix(prefix) => write!(f, "Invalid network prefix length: {}", prefix),
+        }
+    }
+}
+
+impl fmt::Display for CidrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl error::Error for CidrError {}
+
+/// Client information resolved by [TrustedProxy] middleware.
+///
+/// It's inserted into request's extensions. Values fall back to the ones of the connection and
+/// request itself when forwarding headers are absent or not trusted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ForwardedInfo {
+    ip: Option<IpAddr>,
+    scheme: Option<String>,
+    host: Option<String>,
+}
+
+impl ForwardedInfo {
+    /// Get ip address of client.
+    ///
+    /// `None` when connection has no ip address or a trusted proxy reports the client as unknown
+    /// or obfuscated.
+    #[inline]
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.ip
+    }
+
+    /// Get the scheme client used to send request.
+    #[inline]
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    /// Get the host client sent request to. Port is included when it's present.
+    #[inline]
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+}
+
+/// A factory for middleware resolving [ForwardedInfo] from trusted proxies.
+#[derive(Clone, Default)]
+pub struct TrustedProxy {
+    proxies: Arc<Vec<Cidr>>,
+}
+
+impl TrustedProxy {
+    /// Construct a middleware with no trusted proxy.
+    ///
+    /// Forwarding headers are always ignored until proxies are added with [TrustedProxy::trust].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust forwarding headers set by proxies inside given network.
+    pub fn trust(mut self, cidr: Cidr) -> Self {
+        Arc::make_mut(&mut self.proxies).push(cidr);
+        self
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.proxies.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    fn resolve<B>(&self, req: &Request<B>) -> ForwardedInfo {
+        let conn = req.extensions().get::<ConnectionInfo>();
+
+        let mut info = ForwardedInfo {
+            ip: conn
+                .and_then(ConnectionInfo::peer_addr)
+                .map(|addr| canonical(addr.ip())),
+            // origin-form request of Http/1 has no scheme in uri. fall back to the connection's.
+            scheme: req
+                .uri()
+                .scheme_str()
+                .or_else(|| conn.map(|conn| if conn.is_tls() { "https" } else { "http" }))
+                .map(str::to_owned),
+            host: req
+                .headers()
+                .get(HOST)
+                .and_then(|v| v.to_str().ok())
+                .or_else(|| req.uri().authority().map(|a| a.as_str()))
+                .map(str::to_owned),
+        };
+
+        match info.ip {
+            Some(ip) if self.is_trusted(ip) => {}
+            _ => return info,
+        }
+
+        let headers = req.headers();
+
+        if headers.contains_key(FORWARDED) {
+            for element in split_values(headers, FORWARDED).rev() {
+                let mut ip = None;
+
+                for pair in split_quoted(element, ';') {
+                    let (key, value) = match pair.split_once('=') {
+                        Some((key, value)) => (key.trim(), unquote(value.trim())),
+                        None => continue,
+                    };
+
+                    if key.eq_ignore_ascii_case("for") {
+                        ip = parse_node(value);
+                    } else if key.eq_ignore_ascii_case("proto") {
+                        info.scheme = Some(value.to_ascii_lowercase());
+                    } else if key.eq_ignore_ascii_case("host") {
+                        info.host = Some(value.to_owned());
+                    }
+                }
+
+                info.ip = ip;
+
+                match ip {
+                    Some(ip) if self.is_trusted(ip) => {}
+                    _ => break,
+                }
+            }
+        } else {
+            for node in split_values(headers, X_FORWARDED_FOR).rev() {
+                let ip = parse_node(unquote(node));
+                info.ip = ip;
+
+                match ip {
+                    Some(ip) if self.is_trusted(ip) => {}
+                    _ => break,
+                }
+            }
+
+            if let Some(proto) = split_values(headers, X_FORWARDED_PROTO).next_back() {
+                info.scheme = Some(proto.to_ascii_lowercase());
+            }
+
+            if let Some(host) = split_values(headers, X_FORWARDED_HOST).next_back() {
+                info.host = Some(host.to_owned());
+            }
+        }
+
+        info
+    }
+}
+
+impl<S, ReqB> Transform<S, Request<ReqB>> for TrustedProxy
+where
+    S: Service<Request<ReqB>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Transform = TrustedProxyService<S>;
+    type InitError = ();
+    type Future = impl Future<Output = Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let config = self.clone();
+        async move { Ok(TrustedProxyService { service, config }) }
+    }
+}
+
+pub struct TrustedProxyService<S> {
+    service: S,
+    config: TrustedProxy,
+}
+
+impl<S, ReqB> Service<Request<ReqB>> for TrustedProxyService<S>
+where
+    S: Service<Request<ReqB>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Ready<'f>
+    where
+        S: 'f,
+    = S::Ready<'f>;
+    type Future<'f>
+    where
+        S: 'f,
+    = S::Future<'f>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        self.service.ready()
+    }
+
+    #[inline]
+    fn call(&self, mut req: Request<ReqB>) -> Self::Future<'_> {
+        let info = self.config.resolve(&req);
+        req.extensions_mut().insert(info);
+        self.service.call(req)
+    }
+}
+
+// map ipv4 mapped ipv6 address to ipv4 address.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.segments() {
+            [0, 0, 0, 0, 0, 0xffff, hi, lo] => IpAddr::V4((((hi as u32) << 16) | lo as u32).into()),
+            _ => ip,
+        },
+        ip => ip,
+    }
+}
+
+// comma separated values of all header lines with given name. non utf-8 lines are skipped.
+fn split_values<K>(headers: &HeaderMap, name: K) -> impl DoubleEndedIterator<Item = &str>
+where
+    K: AsHeaderName,
+{
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| split_quoted(v, ','))
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+// split string by separator that is not inside a quoted string. parts are trimmed.
+fn split_quoted(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (idx, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == sep && !quoted => {
+                parts.push(s[start..idx].trim());
+                start = idx + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(s[start..].trim());
+    parts
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(s)
+}
+
+// parse node identifier in the form of `ip`, `ip:port`, `[ipv6]` or `[ipv6]:port`.
+// `unknown` and obfuscated identifiers are resolved to None.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    node.parse::<IpAddr>()
+        .or_else(|_| node.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .or_else(|_| {
+            node.strip_prefix('[')
+                .and_then(|s| s.strip_suffix(']'))
+                .unwrap_or_default()
+                .parse::<IpAddr>()
+        })
+        .ok()
+        .map(canonical)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(peer: &str, headers: &[(&'static str, &str)]) -> Request<()> {
+        let mut req = Request::new(());
+        req.headers_mut().insert(HOST, "internal:8080".parse().unwrap());
+        for (name, value) in headers {
+            req.headers_mut().append(*name, value.parse().unwrap());
+        }
+        let peer = SocketAddr::new(peer.parse().unwrap(), 1234);
+        req.extensions_mut().insert(ConnectionInfo::new(Some(peer), None));
+        req
+    }
+
+    fn proxy() -> TrustedProxy {
+        TrustedProxy::new()
+            .trust("10.0.0.0/8".parse().unwrap())
+            .trust("fd00::/8".parse().unwrap())
+    }
+
+    #[test]
+    fn cidr() {
+        let cidr = "10.0.0.0/8".parse::<Cidr>().unwrap();
+        assert!(cidr.contains("10.1.2.3".parse().unwrap()));
+        assert!(cidr.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!cidr.contains("11.0.0.1".parse().unwrap()));
+
+        let cidr = "0.0.0.0/0".parse::<Cidr>().unwrap();
+        assert!(cidr.contains("1.2.3.4".parse().unwrap()));
+        assert!(!cidr.contains("::1".parse().unwrap()));
+
+        let cidr = "::1".parse::<Cidr>().unwrap();
+        assert!(cidr.contains("::1".parse().unwrap()));
+        assert!(!cidr.contains("::2".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("10.0.0/8".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn node() {
+        assert_eq!(parse_node("1.2.3.4"), Some("1.2.3.4".parse().unwrap()));
+        assert_eq!(parse_node("1.2.3.4:80"), Some("1.2.3.4".parse().unwrap()));
+        assert_eq!(parse_node("[::1]:80"), Some("::1".parse().unwrap()));
+        assert_eq!(parse_node("[::1]"), Some("::1".parse().unwrap()));
+        assert_eq!(parse_node("::1"), Some("::1".parse().unwrap()));
+        assert_eq!(parse_node("unknown"), None);
+        assert_eq!(parse_node("_hidden"), None);
+    }
+
+    #[test]
+    fn untrusted_peer() {
+        let req = request(
+            "1.2.3.4",
+            &[("x-forwarded-for", "5.6.7.8"), ("x-forwarded-proto", "https")],
+        );
+        let info = proxy().resolve(&req);
+        assert_eq!(info.ip(), Some("1.2.3.4".parse().unwrap()));
+        assert_eq!(info.scheme(), Some("http"));
+        assert_eq!(info.host(), Some("internal:8080"));
+
+        // scheme of tls connection.
+        let mut req = req;
+        let conn = req.extensions_mut().remove::<ConnectionInfo>().unwrap();
+        req.extensions_mut().insert(conn.tls(true));
+        let info = proxy().resolve(&req);
+        assert_eq!(info.scheme(), Some("https"));
+    }
+
+    #[test]
+    fn x_forwarded() {
+        let req = request(
+            "10.0.0.1",
+            &[
+                ("x-forwarded-for", "9.9.9.9, 5.6.7.8"),
+                ("x-forwarded-for", "10.0.0.2"),
+                ("x-forwarded-proto", "HTTPS"),
+                ("x-forwarded-host", "example.com"),
+            ],
+        );
+        let info = proxy().resolve(&req);
+        assert_eq!(info.ip(), Some("5.6.7.8".parse().unwrap()));
+        assert_eq!(info.scheme(), Some("https"));
+        assert_eq!(info.host(), Some("example.com"));
+    }
+
+    #[test]
+    fn forwarded() {
+        let req = request(
+            "fd00::1",
+            &[
+                ("forwarded", "for=9.9.9.9;proto=http"),
+                (
+                    "forwarded",
+                    "for=\"[2001:db8::1]:4711\";proto=https;host=\"example.com\", for=10.0.0.2",
+                ),
+                ("x-forwarded-for", "5.6.7.8"),
+            ],
+        );
+        let info = proxy().resolve(&req);
+        assert_eq!(info.ip(), Some("2001:db8::1".parse().unwrap()));
+        assert_eq!(info.scheme(), Some("https"));
+        assert_eq!(info.host(), Some("example.com"));
+
+        let req = request("10.0.0.1", &[("forwarded", "for=unknown;proto=https")]);
+        let info = proxy().resolve(&req);
+        assert_eq!(info.ip(), None);
+        assert_eq!(info.scheme(), Some("https"));
+    }
+}
@@ -0,0 +1,500 @@
+//! Prometheus style metrics middleware and exporter service.
+//!
+//! # Example:
+//! ```rust,ignore
+//! use xitca_http::util::middleware::{Metrics, MetricsRegistry};
+//!
+//! let registry = MetricsRegistry::new();
+//!
+//! // export connection count of xitca-server.
+//! let builder = xitca_server::Builder::new();
+//! let counter = builder.connection_counter();
+//! registry.register_gauge("xitca_server_connections", "Number of living connections.", move || counter.get() as f64);
+//!
+//! let factory = move || {
+//!     let router = Router::new()
+//!         .insert("/", get(fn_service(index)))
+//!         .insert("/metrics", get(registry.exporter().map_err(|e| match e {})));
+//!     HttpServiceBuilder::new(router.transform(Metrics::new(registry.clone())))
+//! };
+//! ```
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt::Write,
+    future::{ready, Future, Ready},
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use xitca_service::{Service, ServiceFactory, Transform};
+
+use crate::{
+    body::ResponseBody,
+    bytes::Bytes,
+    http::{
+        header::{HeaderMap, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE},
+        IntoResponse, Request, Response, StatusCode,
+    },
+    util::service::{MatchedPath, MatchedPathSlot},
+};
+
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+const SIZE_BUCKETS: &[f64] = &[
+    64.0, 256.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0,
+];
+
+/// Storage of collected metrics.
+///
+/// Registry is thread safe and cheap to clone. One registry is meant to be shared by all
+/// worker threads of a server and both [Metrics] middleware and [MetricsExporter] service.
+#[derive(Clone, Default)]
+pub struct MetricsRegistry {
+    inner: Arc<RegistryInner>,
+}
+
+#[derive(Default)]
+struct RegistryInner {
+    in_flight: AtomicI64,
+    series: Mutex<HashMap<SeriesKey, Series>>,
+    gauges: Mutex<Vec<Gauge>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct SeriesKey {
+    route: &'static str,
+    // None when inner service returns error.
+    status: Option<StatusCode>,
+}
+
+struct Series {
+    requests: u64,
+    latency: Histogram,
+    request_size: Histogram,
+    response_size: Histogram,
+}
+
+impl Series {
+    fn new() -> Self {
+        Self {
+            requests: 0,
+            latency: Histogram::new(LATENCY_BUCKETS),
+            request_size: Histogram::new(SIZE_BUCKETS),
+            response_size: Histogram::new(SIZE_BUCKETS),
+        }
+    }
+}
+
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(idx) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.counts[idx] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    func: Box<dyn Fn() -> f64 + Send + Sync>,
+}
+
+impl MetricsRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a gauge that is evaluated every time metrics are rendered.
+    ///
+    /// This is useful for exporting values owned by other components.
+    /// (e.g. `xitca_server::ConnectionCounter`)
+    pub fn register_gauge<F>(&self, name: &'static str, help: &'static str, func: F)
+    where
+        F: Fn() -> f64 + Send + Sync + 'static,
+    {
+        self.inner.gauges.lock().unwrap().push(Gauge {
+            name,
+            help,
+            func: Box::new(func),
+        });
+    }
+
+    /// Construct a service factory that render metrics in Prometheus text format.
+    pub fn exporter(&self) -> MetricsExporter {
+        MetricsExporter { registry: self.clone() }
+    }
+
+    /// Render collected metrics in Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buf = String::new();
+
+        {
+            let gauges = self.inner.gauges.lock().unwrap();
+            for gauge in gauges.iter() {
+                header(&mut buf, gauge.name, gauge.help, "gauge");
+                let _ = writeln!(buf, "{} {}", gauge.name, (gauge.func)());
+            }
+        }
+
+        header(
+            &mut buf,
+            "http_requests_in_flight",
+            "Number of http requests currently being served.",
+            "gauge",
+        );
+        let _ = writeln!(
+            buf,
+            "http_requests_in_flight {}",
+            self.inner.in_flight.load(Ordering::Relaxed)
+        );
+
+        let series = self.inner.series.lock().unwrap();
+
+        // sort series for stable output.
+        let mut series = series.iter().collect::<Vec<_>>();
+        series.sort_by_key(|(key, _)| (key.route, key.status.map(|s| s.as_u16())));
+
+        header(
+            &mut buf,
+            "http_requests_total",
+            "Total number of http requests.",
+            "counter",
+        );
+        for (key, series) in series.iter() {
+            let _ = writeln!(buf, "http_requests_total{{{}}} {}", labels(key), series.requests);
+        }
+
+        let histograms: [(&str, &str, fn(&Series) -> &Histogram); 3] = [
+            (
+                "http_request_duration_seconds",
+                "Latency of http requests in seconds.",
+                |s| &s.latency,
+            ),
+            (
+                "http_request_size_bytes",
+                "Size of http request bodies in bytes.",
+                |s| &s.request_size,
+            ),
+            (
+                "http_response_size_bytes",
+                "Size of http response bodies in bytes.",
+                |s| &s.response_size,
+            ),
+        ];
+
+        for (name, help, get) in histograms {
+            header(&mut buf, name, help, "histogram");
+            for (key, series) in series.iter() {
+                render_histogram(&mut buf, name, &labels(key), get(series));
+            }
+        }
+
+        buf
+    }
+
+    fn record(&self, key: SeriesKey, latency: Duration, request_size: Option<u64>, response_size: Option<u64>) {
+        let mut series = self.inner.series.lock().unwrap();
+        let series = series.entry(key).or_insert_with(Series::new);
+
+        series.requests += 1;
+        series.latency.observe(latency.as_secs_f64());
+        if let Some(size) = request_size {
+            series.request_size.observe(size as f64);
+        }
+        if let Some(size) = response_size {
+            series.response_size.observe(size as f64);
+        }
+    }
+}
+
+fn header(buf: &mut String, name: &str, help: &str, ty: &str) {
+    let _ = writeln!(buf, "# HELP {} {}", name, help);
+    let _ = writeln!(buf, "# TYPE {} {}", name, ty);
+}
+
+fn labels(key: &SeriesKey) -> String {
+    let mut buf = String::from("route=\"");
+    for c in key.route.chars() {
+        match c {
+            '\\' => buf.push_str("\\\\"),
+            '"' => buf.push_str("\\\""),
+            '\n' => buf.push_str("\\n"),
+            c => buf.push(c),
+        }
+    }
+    buf.push_str("\",status=\"");
+    match key.status {
+        Some(status) => buf.push_str(status.as_str()),
+        None => buf.push_str("error"),
+    }
+    buf.push('"');
+    buf
+}
+
+fn render_histogram(buf: &mut String, name: &str, labels: &str, histogram: &Histogram) {
+    let mut cumulative = 0;
+    for (bound, count) in histogram.bounds.iter().zip(histogram.counts.iter()) {
+        cumulative += count;
+        let _ = writeln!(buf, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, bound, cumulative);
+    }
+    let _ = writeln!(buf, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, histogram.count);
+    let _ = writeln!(buf, "{}_sum{{{}}} {}", name, labels, histogram.sum);
+    let _ = writeln!(buf, "{}_count{{{}}} {}", name, labels, histogram.count);
+}
+
+/// A factory for metrics collecting service.
+///
+/// Requests are labelled by route pattern and response status code. Route pattern is the
+/// [MatchedPath] recorded by [Router](crate::util::service::Router) when middleware wraps it, or
+/// the one found in request's extensions when middleware is applied to a route.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: MetricsRegistry,
+}
+
+impl Metrics {
+    pub fn new(registry: MetricsRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+impl<S, ReqB, ResB> Transform<S, Request<ReqB>> for Metrics
+where
+    S: Service<Request<ReqB>, Response = Response<ResponseBody<ResB>>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Transform = MetricsService<S>;
+    type InitError = ();
+    type Future = impl Future<Output = Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let registry = self.registry.clone();
+        async move { Ok(MetricsService { service, registry }) }
+    }
+}
+
+pub struct MetricsService<S> {
+    service: S,
+    registry: MetricsRegistry,
+}
+
+impl<S, ReqB, ResB> Service<Request<ReqB>> for MetricsService<S>
+where
+    S: Service<Request<ReqB>, Response = Response<ResponseBody<ResB>>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Ready<'f>
+    where
+        S: 'f,
+    = S::Ready<'f>;
+    type Future<'f>
+    where
+        S: 'f,
+    = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        self.service.ready()
+    }
+
+    fn call(&self, mut req: Request<ReqB>) -> Self::Future<'_> {
+        async move {
+            let _guard = InFlightGuard::new(&self.registry);
+
+            let request_size = content_length(req.headers());
+
+            let matched = req.extensions().get::<MatchedPath>().copied();
+            let slot = MatchedPathSlot::default();
+            req.extensions_mut().insert(slot.clone());
+
+            let start = Instant::now();
+
+            let res = self.service.call(req).await;
+
+            let latency = start.elapsed();
+
+            let route = slot.get().or(matched).as_ref().map_or("", MatchedPath::as_str);
+
+            match res {
+                Ok(ref res) => {
+                    let key = SeriesKey {
+                        route,
+                        status: Some(res.status()),
+                    };
+                    let response_size = match res.body() {
+                        ResponseBody::None => Some(0),
+                        ResponseBody::Bytes { bytes } => Some(bytes.len() as u64),
+                        ResponseBody::Stream { .. } => content_length(res.headers()),
+                    };
+                    self.registry.record(key, latency, request_size, response_size);
+                }
+                Err(_) => {
+                    let key = SeriesKey { route, status: None };
+                    self.registry.record(key, latency, request_size, None);
+                }
+            }
+
+            res
+        }
+    }
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+// guard type for decrementing in flight gauge when request future finished or dropped.
+struct InFlightGuard<'a>(&'a MetricsRegistry);
+
+impl<'a> InFlightGuard<'a> {
+    fn new(registry: &'a MetricsRegistry) -> Self {
+        registry.inner.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(registry)
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.inner.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Service factory for exporting metrics from [MetricsRegistry] in Prometheus text format.
+#[derive(Clone)]
+pub struct MetricsExporter {
+    registry: MetricsRegistry,
+}
+
+impl<ReqB> ServiceFactory<Request<ReqB>> for MetricsExporter {
+    type Response = Response<ResponseBody>;
+    type Error = Infallible;
+    type Config = ();
+    type Service = Self;
+    type InitError = ();
+    type Future = impl Future<Output = Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: Self::Config) -> Self::Future {
+        let this = self.clone();
+        async { Ok(this) }
+    }
+}
+
+impl<ReqB> Service<Request<ReqB>> for MetricsExporter {
+    type Response = Response<ResponseBody>;
+    type Error = Infallible;
+    type Ready<'f>
+    where
+        Self: 'f,
+    = Ready<Result<(), Self::Error>>;
+    type Future<'f>
+    where
+        Self: 'f,
+    = Ready<Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        ready(Ok(()))
+    }
+
+    fn call(&self, req: Request<ReqB>) -> Self::Future<'_> {
+        let body = Bytes::from(self.registry.render());
+        let mut res = req.into_response(body);
+        res.headers_mut().insert(CONTENT_TYPE, TEXT_FORMAT);
+        ready(Ok(res))
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const TEXT_FORMAT: HeaderValue = HeaderValue::from_static("text/plain; version=0.0.4; charset=utf-8");
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let registry = MetricsRegistry::new();
+        registry.register_gauge("connections", "living connections", || 3.0);
+
+        let key = SeriesKey {
+            route: "/foo",
+            status: Some(StatusCode::OK),
+        };
+        registry.record(key, Duration::from_millis(20), Some(100), Some(2048));
+        registry.record(key, Duration::from_secs(20), None, Some(2048));
+
+        let text = registry.render();
+
+        assert!(text.contains("connections 3\n"));
+        assert!(text.contains("http_requests_in_flight 0\n"));
+        assert!(text.contains("http_requests_total{route=\"/foo\",status=\"200\"} 2\n"));
+        assert!(text.contains("http_request_duration_seconds_bucket{route=\"/foo\",status=\"200\",le=\"0.025\"} 1\n"));
+        assert!(text.contains("http_request_duration_seconds_bucket{route=\"/foo\",status=\"200\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("http_request_size_bytes_count{route=\"/foo\",status=\"200\"} 1\n"));
+        assert!(text.contains("http_response_size_bytes_bucket{route=\"/foo\",status=\"200\",le=\"4096\"} 2\n"));
+    }
+
+    #[tokio::test]
+    async fn middleware() {
+        use xitca_service::{fn_service, ServiceFactoryExt};
+
+        use crate::{body::RequestBody, http::Uri, util::service::Router};
+
+        async fn handler(_: Request<RequestBody>) -> Result<Response<ResponseBody>, Infallible> {
+            Ok(Response::new(ResponseBody::bytes(Bytes::from_static(b"hello"))))
+        }
+
+        let registry = MetricsRegistry::new();
+
+        let service = Router::new()
+            .insert("/users/:id", fn_service(handler))
+            .transform(Metrics::new(registry.clone()))
+            .new_service(())
+            .await
+            .ok()
+            .unwrap();
+
+        let mut req = Request::new(RequestBody::None);
+        *req.uri_mut() = Uri::from_static("/users/1");
+        let res = service.call(req).await.ok().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let mut req = Request::new(RequestBody::None);
+        *req.uri_mut() = Uri::from_static("/foo");
+        assert!(service.call(req).await.is_err());
+
+        let text = registry.render();
+
+        assert!(text.contains("http_requests_in_flight 0\n"));
+        assert!(text.contains("http_requests_total{route=\"/users/:id\",status=\"200\"} 1\n"));
+        assert!(text.contains("http_response_size_bytes_sum{route=\"/users/:id\",status=\"200\"} 5\n"));
+        assert!(text.contains("http_requests_total{route=\"\",status=\"error\"} 1\n"));
+    }
+}
@@ -1,5 +1,17 @@
+mod cache;
+mod etag;
+mod forwarded;
 mod logger;
+mod metrics;
+mod rate_limit;
 mod tcp_config;
 
+pub use cache::{Cache, CacheService};
+pub use etag::{ETag, ETagService, NoValidators, Preconditions, Validators, ValidatorsLookup};
+pub use forwarded::{Cidr, CidrError, ForwardedInfo, TrustedProxy, TrustedProxyService};
 pub use logger::Logger;
+pub use metrics::{Metrics, MetricsExporter, MetricsRegistry, MetricsService};
+pub use rate_limit::{
+    Decision, LocalState, LocalStore, Quota, RateLimit, RateLimitService, RateLimitState, RateLimitStore, SharedStore,
+};
 pub use tcp_config::TcpConfig;
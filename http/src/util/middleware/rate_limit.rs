@@ -0,0 +1,500 @@
+//! Rate limiting middleware based on generic cell rate algorithm(GCRA).
+//!
+//! # Example:
+//! ```rust,ignore
+//! use xitca_http::util::middleware::{Quota, RateLimit};
+//!
+//! // limit every client to 10 requests per second with a burst of 20 requests.
+//! // clients are identified by their api key header.
+//! let limit = RateLimit::new(Quota::per_second(10).burst(20), |req: &Request<_>| {
+//!     req.headers().get("x-api-key").cloned()
+//! });
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    cmp,
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use xitca_service::{Service, Transform};
+
+use crate::{
+    body::ResponseBody,
+    http::{
+        header::{HeaderMap, RETRY_AFTER},
+        IntoResponse, Request, Response, StatusCode,
+    },
+};
+
+// stale keys are removed from store every time this many requests are checked.
+const CLEAN_INTERVAL: usize = 1024;
+
+/// Quota of a rate limiter.
+#[derive(Debug, Clone, Copy)]
+pub struct Quota {
+    // time it takes to replenish one request.
+    interval: Duration,
+    burst: u32,
+}
+
+impl Quota {
+    /// Construct a quota allowing `n` requests in given period of time.
+    /// Burst size is set to `n` by default.
+    ///
+    /// Replenish interval of one request is clamped to at least 1 nanosecond so a period
+    /// shorter than `n` nanoseconds allows fewer than `n` requests in it.
+    ///
+    /// # Panics:
+    /// When `n` is 0 or period is zero.
+    pub fn with_period(n: u32, period: Duration) -> Self {
+        assert_ne!(n, 0, "Quota must allow at least one request");
+        assert!(!period.is_zero(), "Quota period must not be zero");
+        Self {
+            interval: cmp::max(period / n, Duration::from_nanos(1)),
+            burst: n,
+        }
+    }
+
+    /// Construct a quota allowing `n` requests per second.
+    pub fn per_second(n: u32) -> Self {
+        Self::with_period(n, Duration::from_secs(1))
+    }
+
+    /// Construct a quota allowing `n` requests per minute.
+    pub fn per_minute(n: u32) -> Self {
+        Self::with_period(n, Duration::from_secs(60))
+    }
+
+    /// Change max number of requests can be made in a burst.
+    ///
+    /// # Panics:
+    /// When `burst` is 0.
+    pub fn burst(mut self, burst: u32) -> Self {
+        assert_ne!(burst, 0, "Burst size must be higher than 0");
+        self.burst = burst;
+        self
+    }
+
+    fn tolerance(&self) -> Duration {
+        self.interval * self.burst
+    }
+
+    /// Check against theoretical arrival time(tat) of a key and update it when request is allowed.
+    ///
+    /// `tat` is `None` for a key that has not been seen. This is the building block for
+    /// implementing [RateLimitState] with a custom storage.
+    pub fn check(&self, tat: &mut Option<Instant>, now: Instant) -> Decision {
+        let tolerance = self.tolerance();
+
+        let new_tat = match *tat {
+            Some(tat) if tat > now => tat,
+            _ => now,
+        } + self.interval;
+
+        let wait = new_tat - now;
+
+        if wait > tolerance {
+            Decision {
+                allowed: false,
+                limit: self.burst,
+                remaining: 0,
+                reset: wait - self.interval,
+                retry_after: wait - tolerance,
+            }
+        } else {
+            *tat = Some(new_tat);
+            let remaining = ((tolerance - wait).as_nanos() / self.interval.as_nanos()) as u32;
+            Decision {
+                allowed: true,
+                limit: self.burst,
+                remaining,
+                reset: wait,
+                retry_after: Duration::ZERO,
+            }
+        }
+    }
+}
+
+/// Outcome of checking a request against [Quota].
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    allowed: bool,
+    limit: u32,
+    remaining: u32,
+    reset: Duration,
+    retry_after: Duration,
+}
+
+impl Decision {
+    /// Construct a decision. Useful for [RateLimitState] implementations that delegate the
+    /// computation to an external service.
+    pub fn new(allowed: bool, limit: u32, remaining: u32, reset: Duration, retry_after: Duration) -> Self {
+        Self {
+            allowed,
+            limit,
+            remaining,
+            reset,
+            retry_after,
+        }
+    }
+
+    /// Whether request is allowed.
+    #[inline]
+    pub fn allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// Max number of requests can be made in a burst.
+    #[inline]
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Number of requests can still be made right now.
+    #[inline]
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+
+    /// Time until the quota is fully replenished.
+    #[inline]
+    pub fn reset(&self) -> Duration {
+        self.reset
+    }
+
+    /// Time to wait before retrying a rejected request. Zero when request is allowed.
+    #[inline]
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+
+    fn write_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(RATELIMIT_LIMIT, self.limit.into());
+        headers.insert(RATELIMIT_REMAINING, self.remaining.into());
+        headers.insert(RATELIMIT_RESET, ceil_secs(self.reset).into());
+        if !self.allowed {
+            headers.insert(RETRY_AFTER, ceil_secs(self.retry_after).into());
+        }
+    }
+}
+
+fn ceil_secs(dur: Duration) -> u64 {
+    let secs = dur.as_secs();
+    if dur.subsec_nanos() > 0 {
+        secs + 1
+    } else {
+        secs
+    }
+}
+
+const RATELIMIT_LIMIT: &str = "ratelimit-limit";
+const RATELIMIT_REMAINING: &str = "ratelimit-remaining";
+const RATELIMIT_RESET: &str = "ratelimit-reset";
+
+/// Storage strategy of rate limiter state.
+///
+/// [RateLimitStore::state] is called once every time the middleware is constructed.
+pub trait RateLimitStore<K>: Clone {
+    type State: RateLimitState<K>;
+
+    fn state(&self) -> Self::State;
+}
+
+/// State of rate limiter keyed by user provided key.
+pub trait RateLimitState<K> {
+    /// Check request with given key against quota.
+    fn check(&self, key: K, quota: &Quota, now: Instant) -> Decision;
+}
+
+/// Default store where every worker thread has its own state.
+///
+/// Every worker thread of xitca-server runs on a single threaded runtime so no synchronization
+/// is needed. The downside is that a client can be given up to `quota * worker_threads`
+/// requests when its connections are spread across workers.
+#[derive(Clone, Copy, Default)]
+pub struct LocalStore;
+
+impl<K> RateLimitStore<K> for LocalStore
+where
+    K: Hash + Eq,
+{
+    type State = LocalState<K>;
+
+    fn state(&self) -> Self::State {
+        LocalState {
+            map: RefCell::new(HashMap::new()),
+            checks: Cell::new(0),
+        }
+    }
+}
+
+pub struct LocalState<K> {
+    map: RefCell<HashMap<K, Option<Instant>>>,
+    checks: Cell<usize>,
+}
+
+impl<K> RateLimitState<K> for LocalState<K>
+where
+    K: Hash + Eq,
+{
+    fn check(&self, key: K, quota: &Quota, now: Instant) -> Decision {
+        let mut map = self.map.borrow_mut();
+        maybe_clean(&self.checks, &mut map, now);
+        quota.check(map.entry(key).or_default(), now)
+    }
+}
+
+/// Store shared by all worker threads.
+///
+/// Construct it outside of server's service factory closure and clone it into
+/// every [RateLimit] to share state between workers.
+pub struct SharedStore<K> {
+    inner: Arc<Mutex<SharedInner<K>>>,
+}
+
+struct SharedInner<K> {
+    map: HashMap<K, Option<Instant>>,
+    checks: Cell<usize>,
+}
+
+impl<K> Clone for SharedStore<K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K> Default for SharedStore<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> SharedStore<K> {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(SharedInner {
+                map: HashMap::new(),
+                checks: Cell::new(0),
+            })),
+        }
+    }
+}
+
+impl<K> RateLimitStore<K> for SharedStore<K>
+where
+    K: Hash + Eq,
+{
+    type State = Self;
+
+    fn state(&self) -> Self::State {
+        self.clone()
+    }
+}
+
+impl<K> RateLimitState<K> for SharedStore<K>
+where
+    K: Hash + Eq,
+{
+    fn check(&self, key: K, quota: &Quota, now: Instant) -> Decision {
+        let mut inner = self.inner.lock().unwrap();
+        let SharedInner { map, checks } = &mut *inner;
+        maybe_clean(checks, map, now);
+        quota.check(map.entry(key).or_default(), now)
+    }
+}
+
+fn maybe_clean<K>(checks: &Cell<usize>, map: &mut HashMap<K, Option<Instant>>, now: Instant) {
+    let n = checks.get() + 1;
+    if n == CLEAN_INTERVAL {
+        checks.set(0);
+        map.retain(|_, tat| matches!(*tat, Some(tat) if tat > now));
+    } else {
+        checks.set(n);
+    }
+}
+
+/// A factory for rate limiting service.
+///
+/// Requests are grouped by the key returned from user provided closure. When a group exceeds
+/// its [Quota] the request is rejected with `429 Too Many Requests` response and `Retry-After`
+/// header. `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` headers are added
+/// to all responses.
+#[derive(Clone)]
+pub struct RateLimit<F, St = LocalStore> {
+    quota: Quota,
+    key: F,
+    store: St,
+}
+
+impl<F> RateLimit<F> {
+    /// Construct a new rate limiter with given quota and key extractor closure.
+    pub fn new(quota: Quota, key: F) -> Self {
+        Self {
+            quota,
+            key,
+            store: LocalStore,
+        }
+    }
+}
+
+impl<F, St> RateLimit<F, St> {
+    /// Change the store of rate limiter state.
+    ///
+    /// See [SharedStore] for sharing state between worker threads.
+    pub fn store<St2>(self, store: St2) -> RateLimit<F, St2> {
+        RateLimit {
+            quota: self.quota,
+            key: self.key,
+            store,
+        }
+    }
+}
+
+impl<S, F, St, K, ReqB, ResB> Transform<S, Request<ReqB>> for RateLimit<F, St>
+where
+    S: Service<Request<ReqB>, Response = Response<ResponseBody<ResB>>>,
+    F: Fn(&Request<ReqB>) -> K + Clone,
+    St: RateLimitStore<K>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Transform = RateLimitService<S, F, St::State>;
+    type InitError = ();
+    type Future = impl Future<Output = Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let quota = self.quota;
+        let key = self.key.clone();
+        let state = self.store.state();
+
+        async move {
+            Ok(RateLimitService {
+                service,
+                quota,
+                key,
+                state,
+            })
+        }
+    }
+}
+
+pub struct RateLimitService<S, F, St> {
+    service: S,
+    quota: Quota,
+    key: F,
+    state: St,
+}
+
+impl<S, F, St, K, ReqB, ResB> Service<Request<ReqB>> for RateLimitService<S, F, St>
+where
+    S: Service<Request<ReqB>, Response = Response<ResponseBody<ResB>>>,
+    F: Fn(&Request<ReqB>) -> K,
+    St: RateLimitState<K>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Ready<'f>
+    where
+        Self: 'f,
+    = S::Ready<'f>;
+    type Future<'f>
+    where
+        Self: 'f,
+    = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        self.service.ready()
+    }
+
+    fn call(&self, req: Request<ReqB>) -> Self::Future<'_> {
+        async move {
+            let key = (self.key)(&req);
+            let decision = self.state.check(key, &self.quota, Instant::now());
+
+            if decision.allowed {
+                let mut res = self.service.call(req).await?;
+                decision.write_headers(res.headers_mut());
+                Ok(res)
+            } else {
+                let mut res = req.into_response(ResponseBody::<ResB>::None);
+                *res.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+                decision.write_headers(res.headers_mut());
+                Ok(res)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gcra() {
+        let quota = Quota::per_second(2).burst(3);
+        let state = <LocalStore as RateLimitStore<&str>>::state(&LocalStore);
+
+        let now = Instant::now();
+
+        for remaining in (0..3).rev() {
+            let decision = state.check("foo", &quota, now);
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+        }
+
+        let decision = state.check("foo", &quota, now);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after, Duration::from_millis(500));
+
+        // other keys are not affected.
+        assert!(state.check("bar", &quota, now).allowed);
+
+        // one request is replenished after interval.
+        let now = now + Duration::from_millis(500);
+        assert!(state.check("foo", &quota, now).allowed);
+        assert!(!state.check("foo", &quota, now).allowed);
+    }
+
+    #[test]
+    fn sub_nanosecond_interval() {
+        let quota = Quota::with_period(u32::MAX, Duration::from_nanos(10));
+        assert_eq!(quota.interval, Duration::from_nanos(1));
+
+        let mut tat = None;
+        let now = Instant::now();
+        assert!(quota.check(&mut tat, now).allowed());
+    }
+
+    #[test]
+    fn custom_state() {
+        // a state keeping a single tat for all keys.
+        struct Global(RefCell<Option<Instant>>);
+
+        impl<K> RateLimitState<K> for Global {
+            fn check(&self, _: K, quota: &Quota, now: Instant) -> Decision {
+                quota.check(&mut self.0.borrow_mut(), now)
+            }
+        }
+
+        let quota = Quota::per_second(1);
+        let state = Global(RefCell::new(None));
+        let now = Instant::now();
+
+        let decision = state.check("foo", &quota, now);
+        assert!(decision.allowed());
+        assert_eq!(decision.limit(), 1);
+        assert_eq!(decision.remaining(), 0);
+
+        let decision = state.check("bar", &quota, now);
+        assert!(!decision.allowed());
+        assert_eq!(decision.retry_after(), Duration::from_secs(1));
+    }
+}
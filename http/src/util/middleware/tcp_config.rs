@@ -134,7 +134,7 @@ where
     fn call(&self, req: ServerStream) -> Self::Future<'_> {
         // Windows OS specific lint.
         #[allow(irrefutable_let_patterns)]
-        if let ServerStream::Tcp(ref tcp) = req {
+        if let ServerStream::Tcp(ref tcp, _) = req {
             self.try_apply_config(tcp);
         }
 
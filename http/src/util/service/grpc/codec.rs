@@ -0,0 +1,189 @@
+use std::{marker::PhantomData, pin::Pin, task::Poll};
+
+use futures_core::Stream;
+use prost::Message;
+
+use crate::{
+    bytes::{Buf, BufMut, Bytes, BytesMut},
+    error::BodyError,
+    util::futures::poll_fn,
+};
+
+use super::status::{Code, Status};
+
+/// length of message prefix. 1 byte compression flag and 4 bytes big endian message length.
+const PREFIX_LEN: usize = 5;
+
+/// max size of a single decoded message.
+const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+/// Encode message with length prefix.
+pub(super) fn encode<M>(msg: &M) -> Bytes
+where
+    M: Message,
+{
+    let len = msg.encoded_len();
+
+    let mut buf = BytesMut::with_capacity(PREFIX_LEN + len);
+    buf.put_u8(0);
+    buf.put_u32(len as u32);
+    // buffer is reserved with enough capacity.
+    msg.encode(&mut buf).unwrap();
+
+    buf.freeze()
+}
+
+/// Stream of length prefixed messages decoded from request body.
+pub struct Streaming<B, M> {
+    body: B,
+    buf: BytesMut,
+    eof: bool,
+    _msg: PhantomData<M>,
+}
+
+impl<B, M> Streaming<B, M>
+where
+    B: Stream<Item = Result<Bytes, BodyError>> + Unpin,
+    M: Message + Default,
+{
+    pub(super) fn new(body: B) -> Self {
+        Self {
+            body,
+            buf: BytesMut::new(),
+            eof: false,
+            _msg: PhantomData,
+        }
+    }
+
+    /// Receive next message. Return None when request body is ended.
+    pub async fn message(&mut self) -> Result<Option<M>, Status> {
+        loop {
+            if let Some(msg) = self.try_decode()? {
+                return Ok(Some(msg));
+            }
+
+            if self.eof {
+                return if self.buf.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(Status::new(
+                        Code::Internal,
+                        "request body ended with incomplete message",
+                    ))
+                };
+            }
+
+            let body = &mut self.body;
+            match poll_fn(|cx| Pin::new(&mut *body).poll_next(cx)).await {
+                Some(Ok(bytes)) => self.buf.extend_from_slice(&bytes),
+                Some(Err(e)) => return Err(Status::new(Code::Internal, e.to_string())),
+                None => self.eof = true,
+            }
+        }
+    }
+
+    fn try_decode(&mut self) -> Result<Option<M>, Status> {
+        if self.buf.len() < PREFIX_LEN {
+            return Ok(None);
+        }
+
+        if self.buf[0] != 0 {
+            return Err(Status::new(Code::Unimplemented, "compressed message is not supported"));
+        }
+
+        let len = u32::from_be_bytes([self.buf[1], self.buf[2], self.buf[3], self.buf[4]]) as usize;
+
+        if len > MAX_MESSAGE_SIZE {
+            return Err(Status::new(Code::ResourceExhausted, "message is too large"));
+        }
+
+        if self.buf.len() < PREFIX_LEN + len {
+            return Ok(None);
+        }
+
+        self.buf.advance(PREFIX_LEN);
+        let bytes = self.buf.split_to(len);
+
+        M::decode(bytes)
+            .map(Some)
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))
+    }
+}
+
+// A stream yields one item.
+pub(super) struct Once<T>(Option<T>);
+
+impl<T> Once<T> {
+    pub(super) fn new(item: T) -> Self {
+        Self(Some(item))
+    }
+}
+
+impl<T> Unpin for Once<T> {}
+
+impl<T> Stream for Once<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, _: &mut std::task::Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.get_mut().0.take())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn round_trip() {
+        let first = encode(&String::from("hello"));
+        let second = encode(&String::from("world"));
+
+        // split messages across body chunks.
+        let mut bytes = BytesMut::new();
+        bytes.extend_from_slice(&first);
+        bytes.extend_from_slice(&second);
+        let (a, b) = bytes.split_at(3);
+
+        let chunks = vec![Ok(Bytes::copy_from_slice(a)), Ok(Bytes::copy_from_slice(b))];
+        let body = Iter(chunks.into_iter());
+
+        let mut stream = Streaming::<_, String>::new(body);
+        assert_eq!(stream.message().await.unwrap().unwrap(), "hello");
+        assert_eq!(stream.message().await.unwrap().unwrap(), "world");
+        assert!(stream.message().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn incomplete() {
+        let msg = encode(&String::from("hello"));
+        let body = Iter(vec![Ok(msg.slice(..msg.len() - 1))].into_iter());
+
+        let mut stream = Streaming::<_, String>::new(body);
+        let status = stream.message().await.unwrap_err();
+        assert_eq!(status.code(), Code::Internal);
+    }
+
+    #[tokio::test]
+    async fn compressed() {
+        let body = Iter(vec![Ok(Bytes::from_static(&[1, 0, 0, 0, 0]))].into_iter());
+
+        let mut stream = Streaming::<_, String>::new(body);
+        let status = stream.message().await.unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+    }
+
+    struct Iter<I>(I);
+
+    impl<I> Unpin for Iter<I> {}
+
+    impl<I> Stream for Iter<I>
+    where
+        I: Iterator<Item = Result<Bytes, BodyError>>,
+    {
+        type Item = Result<Bytes, BodyError>;
+
+        fn poll_next(self: Pin<&mut Self>, _: &mut std::task::Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.get_mut().0.next())
+        }
+    }
+}
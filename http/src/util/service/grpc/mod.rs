@@ -0,0 +1,464 @@
+//! gRPC service for serving prost generated message types.
+//!
+//! [Grpc] route requests by their `/package.Service/Method` path and handle length prefixed
+//! message framing, `grpc-timeout` deadline and `grpc-status`/`grpc-message` trailers.
+//! It's a [ServiceFactory] and can be inserted into [Router](super::Router) alongside other
+//! Http services.
+//!
+//! gRPC requires Http/2 trailers. It should be served by Http/2 (or Http/3) capable HttpService.
+//!
+//! # Examples:
+//! ```rust,ignore
+//! use xitca_http::util::service::{grpc::{Grpc, Status}, Router};
+//!
+//! let grpc = Grpc::new()
+//!     .unary("/helloworld.Greeter/SayHello", |req: Request<HelloRequest>| async move {
+//!         let name = req.into_body().name;
+//!         Ok::<_, Status>(HelloReply { message: format!("Hello {}", name) })
+//!     });
+//!
+//! let router = Router::new()
+//!     .insert("/helloworld.Greeter/*method", grpc)
+//!     .insert("/health", get(fn_service(health)));
+//! ```
+
+mod codec;
+mod status;
+
+pub use codec::Streaming;
+pub use status::{Code, Status};
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_core::{future::LocalBoxFuture, ready, Stream};
+use prost::Message;
+use tokio::time::{sleep_until, timeout_at, Instant, Sleep};
+use xitca_service::{Service, ServiceFactory};
+
+use crate::{
+    body::{RequestBody, ResponseBody, StreamBody, Trailers},
+    bytes::Bytes,
+    error::BodyError,
+    http::{header::CONTENT_TYPE, HeaderMap, HeaderValue, Request, Response, StatusCode},
+};
+
+use self::codec::{encode, Once};
+
+type Handler<ReqB> = Rc<dyn Fn(Request<ReqB>) -> LocalBoxFuture<'static, Response<ResponseBody>>>;
+
+/// gRPC method router.
+pub struct Grpc<ReqB = RequestBody> {
+    methods: HashMap<&'static str, Handler<ReqB>>,
+}
+
+impl<ReqB> Default for Grpc<ReqB> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<ReqB> Clone for Grpc<ReqB> {
+    fn clone(&self) -> Self {
+        Self {
+            methods: self.methods.clone(),
+        }
+    }
+}
+
+impl<ReqB> Grpc<ReqB> {
+    pub fn new() -> Self {
+        Self {
+            methods: HashMap::new(),
+        }
+    }
+}
+
+impl<ReqB> Grpc<ReqB>
+where
+    ReqB: Stream<Item = Result<Bytes, BodyError>> + Unpin + 'static,
+{
+    /// Insert an unary method handler to given path.
+    ///
+    /// Request's body is the decoded request message. Request's headers are gRPC metadata.
+    ///
+    /// # Panic:
+    ///
+    /// When multiple handlers inserted with the same path.
+    pub fn unary<F, Fut, M, R>(self, path: &'static str, f: F) -> Self
+    where
+        F: Fn(Request<M>) -> Fut + 'static,
+        Fut: Future<Output = Result<R, Status>> + 'static,
+        M: Message + Default + 'static,
+        R: Message + 'static,
+    {
+        let f = Rc::new(f);
+        self.streaming(path, move |req: Request<Streaming<ReqB, M>>| {
+            let f = f.clone();
+            async move {
+                let (parts, mut body) = req.into_parts();
+
+                let msg = body
+                    .message()
+                    .await?
+                    .ok_or_else(|| Status::new(Code::Internal, "request message is missing"))?;
+
+                if body.message().await?.is_some() {
+                    return Err(Status::new(Code::Internal, "unary request has more than one message"));
+                }
+
+                let res = f(Request::from_parts(parts, msg)).await?;
+
+                Ok(Once::new(Ok(res)))
+            }
+        })
+    }
+
+    /// Insert a streaming method handler to given path.
+    ///
+    /// Request's body is a [Streaming] of request messages and handler output a stream of
+    /// response messages. It covers client streaming, server streaming and bidirectional
+    /// streaming methods.
+    ///
+    /// # Panic:
+    ///
+    /// When multiple handlers inserted with the same path.
+    pub fn streaming<F, Fut, M, S, R>(mut self, path: &'static str, f: F) -> Self
+    where
+        F: Fn(Request<Streaming<ReqB, M>>) -> Fut + 'static,
+        Fut: Future<Output = Result<S, Status>> + 'static,
+        M: Message + Default + 'static,
+        S: Stream<Item = Result<R, Status>> + 'static,
+        R: Message + 'static,
+    {
+        let f = Rc::new(f);
+        let handler = move |req: Request<ReqB>| {
+            let f = f.clone();
+            Box::pin(async move {
+                let deadline = grpc_timeout(req.headers()).map(|dur| Instant::now() + dur);
+
+                let (parts, body) = req.into_parts();
+                let req = Request::from_parts(parts, Streaming::new(body));
+
+                let res = match deadline {
+                    Some(deadline) => timeout_at(deadline, f(req))
+                        .await
+                        .unwrap_or_else(|_| Err(deadline_exceeded())),
+                    None => f(req).await,
+                };
+
+                match res {
+                    Ok(stream) => grpc_response::<R>(Box::pin(stream), deadline),
+                    Err(status) => grpc_response::<R>(Box::pin(Once::new(Err(status))), None),
+                }
+            }) as LocalBoxFuture<'static, _>
+        };
+
+        assert!(self.methods.insert(path, Rc::new(handler)).is_none());
+        self
+    }
+}
+
+impl<ReqB> ServiceFactory<Request<ReqB>> for Grpc<ReqB> {
+    type Response = Response<ResponseBody>;
+    type Error = Infallible;
+    type Config = ();
+    type Service = GrpcService<ReqB>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: Self::Config) -> Self::Future {
+        ready(Ok(GrpcService {
+            methods: Rc::new(self.methods.clone()),
+        }))
+    }
+}
+
+pub struct GrpcService<ReqB> {
+    methods: Rc<HashMap<&'static str, Handler<ReqB>>>,
+}
+
+impl<ReqB> Clone for GrpcService<ReqB> {
+    fn clone(&self) -> Self {
+        Self {
+            methods: self.methods.clone(),
+        }
+    }
+}
+
+impl<ReqB> Service<Request<ReqB>> for GrpcService<ReqB> {
+    type Response = Response<ResponseBody>;
+    type Error = Infallible;
+    type Ready<'f>
+    where
+        Self: 'f,
+    = Ready<Result<(), Self::Error>>;
+    type Future<'f>
+    where
+        Self: 'f,
+    = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        ready(Ok(()))
+    }
+
+    fn call(&self, req: Request<ReqB>) -> Self::Future<'_> {
+        async move {
+            let is_grpc = req
+                .headers()
+                .get(CONTENT_TYPE)
+                .map(|v| is_grpc_content_type(v.as_bytes()))
+                .unwrap_or(false);
+
+            if !is_grpc {
+                let mut res = Response::new(ResponseBody::None);
+                *res.status_mut() = StatusCode::UNSUPPORTED_MEDIA_TYPE;
+                return Ok(res);
+            }
+
+            match self.methods.get(req.uri().path()) {
+                Some(handler) => Ok(handler(req).await),
+                None => {
+                    let status = Status::new(Code::Unimplemented, "method is not implemented");
+                    Ok(grpc_response::<()>(Box::pin(Once::new(Err(status))), None))
+                }
+            }
+        }
+    }
+}
+
+// content-type of gRPC is `application/grpc` optionally followed by a `+` sub type or `;`
+// parameters. `application/grpc-web` carries trailers in body and is not served.
+fn is_grpc_content_type(value: &[u8]) -> bool {
+    match value.strip_prefix(b"application/grpc") {
+        Some(rest) => matches!(rest.first(), None | Some(b'+') | Some(b';')),
+        None => false,
+    }
+}
+
+// construct response with body of encoded messages. trailers are set when body ends.
+fn grpc_response<R>(
+    stream: Pin<Box<dyn Stream<Item = Result<R, Status>>>>,
+    deadline: Option<Instant>,
+) -> Response<ResponseBody>
+where
+    R: Message + 'static,
+{
+    let trailers = Trailers::new();
+
+    let body = GrpcBody {
+        stream: Some(stream),
+        deadline: deadline.map(|d| Box::pin(sleep_until(d))),
+        trailers: trailers.clone(),
+    };
+
+    let mut res = Response::new(ResponseBody::stream(Box::pin(body) as StreamBody));
+    res.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+    res.extensions_mut().insert(trailers);
+
+    res
+}
+
+struct GrpcBody<R> {
+    stream: Option<Pin<Box<dyn Stream<Item = Result<R, Status>>>>>,
+    deadline: Option<Pin<Box<Sleep>>>,
+    trailers: Trailers,
+}
+
+impl<R> GrpcBody<R> {
+    fn finish(&mut self, status: Status) -> Poll<Option<Result<Bytes, BodyError>>> {
+        self.stream = None;
+        self.trailers.set(status.to_trailers());
+        Poll::Ready(None)
+    }
+}
+
+impl<R> Stream for GrpcBody<R>
+where
+    R: Message,
+{
+    type Item = Result<Bytes, BodyError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        let stream = match this.stream.as_mut() {
+            Some(stream) => stream,
+            None => return Poll::Ready(None),
+        };
+
+        if let Some(deadline) = this.deadline.as_mut() {
+            if deadline.as_mut().poll(cx).is_ready() {
+                return this.finish(deadline_exceeded());
+            }
+        }
+
+        match ready!(stream.as_mut().poll_next(cx)) {
+            Some(Ok(msg)) => Poll::Ready(Some(Ok(encode(&msg)))),
+            Some(Err(status)) => this.finish(status),
+            None => this.finish(Status::ok()),
+        }
+    }
+}
+
+fn deadline_exceeded() -> Status {
+    Status::new(Code::DeadlineExceeded, "deadline exceeded")
+}
+
+// parse grpc-timeout header. value is at most 8 digits followed by a time unit.
+fn grpc_timeout(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get("grpc-timeout")?.to_str().ok()?;
+
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+
+    let (num, unit) = value.split_at(value.len() - 1);
+    let num = num.parse::<u64>().ok()?;
+
+    let dur = match unit {
+        "H" => Duration::from_secs(num.checked_mul(3600)?),
+        "M" => Duration::from_secs(num.checked_mul(60)?),
+        "S" => Duration::from_secs(num),
+        "m" => Duration::from_millis(num),
+        "u" => Duration::from_micros(num),
+        "n" => Duration::from_nanos(num),
+        _ => return None,
+    };
+
+    Some(dur)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::util::futures::poll_fn;
+
+    use super::*;
+
+    fn request(path: &str, body: Bytes) -> Request<RequestBody> {
+        let mut req = Request::new(RequestBody::Bytes(Some(body)));
+        *req.uri_mut() = path.parse().unwrap();
+        req.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+        req
+    }
+
+    async fn collect(res: Response<ResponseBody>) -> (Bytes, HeaderMap) {
+        let (mut parts, body) = res.into_parts();
+        let trailers = parts.extensions.remove::<Trailers>().unwrap();
+
+        let mut body = Box::pin(body);
+        let mut buf = Vec::new();
+        while let Some(bytes) = poll_fn(|cx| body.as_mut().poll_next(cx)).await {
+            buf.extend_from_slice(&bytes.unwrap());
+        }
+
+        (Bytes::from(buf), trailers.take().unwrap())
+    }
+
+    #[test]
+    fn timeout() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(grpc_timeout(&headers), None);
+
+        headers.insert("grpc-timeout", HeaderValue::from_static("100m"));
+        assert_eq!(grpc_timeout(&headers), Some(Duration::from_millis(100)));
+
+        headers.insert("grpc-timeout", HeaderValue::from_static("2H"));
+        assert_eq!(grpc_timeout(&headers), Some(Duration::from_secs(7200)));
+
+        headers.insert("grpc-timeout", HeaderValue::from_static("123456789S"));
+        assert_eq!(grpc_timeout(&headers), None);
+
+        headers.insert("grpc-timeout", HeaderValue::from_static("1x"));
+        assert_eq!(grpc_timeout(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn unary() {
+        let service = Grpc::<RequestBody>::new()
+            .unary("/test.Echo/Echo", |req: Request<String>| async move {
+                Ok::<_, Status>(req.into_body())
+            })
+            .new_service(())
+            .await
+            .unwrap();
+
+        let req = request("/test.Echo/Echo", encode(&String::from("hello")));
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "application/grpc");
+
+        let (body, trailers) = collect(res).await;
+        assert_eq!(body, encode(&String::from("hello")));
+        assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+
+        let req = request("/test.Echo/Unknown", Bytes::new());
+        let (body, trailers) = collect(service.call(req).await.unwrap()).await;
+        assert!(body.is_empty());
+        assert_eq!(trailers.get("grpc-status").unwrap(), "12");
+
+        // unary request with extra message.
+        let mut msgs = encode(&String::from("hello")).to_vec();
+        msgs.extend_from_slice(&encode(&String::from("world")));
+        let req = request("/test.Echo/Echo", Bytes::from(msgs));
+        let (body, trailers) = collect(service.call(req).await.unwrap()).await;
+        assert!(body.is_empty());
+        assert_eq!(trailers.get("grpc-status").unwrap(), "13");
+    }
+
+    #[tokio::test]
+    async fn deadline() {
+        let service = Grpc::<RequestBody>::new()
+            .unary("/test.Echo/Sleep", |req: Request<String>| async move {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                Ok::<_, Status>(req.into_body())
+            })
+            .new_service(())
+            .await
+            .unwrap();
+
+        let mut req = request("/test.Echo/Sleep", encode(&String::from("hello")));
+        req.headers_mut().insert("grpc-timeout", HeaderValue::from_static("1m"));
+
+        let (body, trailers) = collect(service.call(req).await.unwrap()).await;
+        assert!(body.is_empty());
+        assert_eq!(trailers.get("grpc-status").unwrap(), "4");
+    }
+
+    #[tokio::test]
+    async fn not_grpc() {
+        let service = Grpc::<RequestBody>::new().new_service(()).await.unwrap();
+
+        let mut req = request("/test.Echo/Echo", Bytes::new());
+        req.headers_mut().remove(CONTENT_TYPE);
+
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let mut req = request("/test.Echo/Echo", Bytes::new());
+        req.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc-web"));
+
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn content_type() {
+        assert!(is_grpc_content_type(b"application/grpc"));
+        assert!(is_grpc_content_type(b"application/grpc+proto"));
+        assert!(is_grpc_content_type(b"application/grpc;charset=utf-8"));
+        assert!(!is_grpc_content_type(b"application/grpc-web"));
+        assert!(!is_grpc_content_type(b"application/grpcx"));
+        assert!(!is_grpc_content_type(b"application/json"));
+    }
+}
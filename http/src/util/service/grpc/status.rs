@@ -0,0 +1,123 @@
+use std::{error, fmt};
+
+use crate::http::{HeaderMap, HeaderValue};
+
+/// gRPC status codes.
+///
+/// See <https://github.com/grpc/grpc/blob/master/doc/statuscodes.md> for meaning of each code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    Ok = 0,
+    Cancelled = 1,
+    Unknown = 2,
+    InvalidArgument = 3,
+    DeadlineExceeded = 4,
+    NotFound = 5,
+    AlreadyExists = 6,
+    PermissionDenied = 7,
+    ResourceExhausted = 8,
+    FailedPrecondition = 9,
+    Aborted = 10,
+    OutOfRange = 11,
+    Unimplemented = 12,
+    Internal = 13,
+    Unavailable = 14,
+    DataLoss = 15,
+    Unauthenticated = 16,
+}
+
+/// gRPC status sent with `grpc-status` and `grpc-message` trailers.
+pub struct Status {
+    code: Code,
+    message: String,
+}
+
+impl Status {
+    pub fn new(code: Code, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Status for successful call.
+    pub fn ok() -> Self {
+        Self::new(Code::Ok, "")
+    }
+
+    #[inline]
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub(super) fn to_trailers(&self) -> HeaderMap {
+        let mut map = HeaderMap::with_capacity(2);
+
+        map.insert("grpc-status", HeaderValue::from(self.code as u16));
+
+        if !self.message.is_empty() {
+            let message = encode_message(&self.message);
+            // percent-encoded message is always visible ascii.
+            map.insert("grpc-message", HeaderValue::from_str(&message).unwrap());
+        }
+
+        map
+    }
+}
+
+// grpc-message is percent-encoded for bytes outside of visible ascii and '%' itself.
+fn encode_message(message: &str) -> String {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    let mut buf = String::with_capacity(message.len());
+
+    for &b in message.as_bytes() {
+        if (0x20..=0x7e).contains(&b) && b != b'%' {
+            buf.push(b as char);
+        } else {
+            buf.push('%');
+            buf.push(HEX[(b >> 4) as usize] as char);
+            buf.push(HEX[(b & 0xf) as usize] as char);
+        }
+    }
+
+    buf
+}
+
+impl fmt::Debug for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Status")
+            .field("code", &self.code)
+            .field("message", &self.message)
+            .finish()
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gRPC status {:?}: {}", self.code, self.message)
+    }
+}
+
+impl error::Error for Status {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trailers() {
+        let map = Status::ok().to_trailers();
+        assert_eq!(map.get("grpc-status").unwrap(), "0");
+        assert!(map.get("grpc-message").is_none());
+
+        let map = Status::new(Code::Unimplemented, "100% not found\n").to_trailers();
+        assert_eq!(map.get("grpc-status").unwrap(), "12");
+        assert_eq!(map.get("grpc-message").unwrap(), "100%25 not found%0A");
+    }
+}
@@ -1,5 +1,9 @@
 mod route;
 mod router;
 
-pub use route::{connect, delete, get, head, options, patch, post, put, trace, Route, RouteError};
-pub use router::{Router, RouterError};
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+pub use route::{connect, delete, get, head, options, patch, post, put, trace, Route, RouteError, RouteResponse};
+pub(crate) use router::MatchedPathSlot;
+pub use router::{MatchedPath, Router, RouterError, TrailingSlash, UrlFor, UrlForError};
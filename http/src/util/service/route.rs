@@ -10,7 +10,15 @@ use std::{
 
 use xitca_service::{Service, ServiceFactory, ServiceFactoryExt};
 
-use crate::http::{Method, Request};
+use crate::{
+    body::ResponseBody,
+    bytes::Bytes,
+    http::{
+        header::{HeaderValue, ALLOW, CONTENT_LENGTH},
+        Method, Request, Response, StatusCode,
+    },
+    response::ResponseError,
+};
 
 macro_rules! method {
     ($method: ident; $($req: ident), *) => {
@@ -43,6 +51,17 @@ macro_rules! method {
 
 macro_rules! route {
     ($($method: ident), *; $($req: ident), *) => {
+        /// Route request to services according to it's method.
+        ///
+        /// Route would answer `OPTIONS` request with registered methods when no service is
+        /// registered for it and answer `HEAD` request with `GET` service (with response body
+        /// removed) when no service is registered for it.
+        /// Request with unregistered method is answered with `405 Method Not Allowed` and
+        /// `Allow` header. See [RouteResponse] for constructing these responses.
+        ///
+        /// Note: Route service requires its response type to implement [RouteResponse]. It's
+        /// implemented for `Response<ResponseBody<B>>` and services with other response types
+        /// have to implement it for their response type to be used with Route.
         pub struct Route<
             Req,
             Res,
@@ -56,6 +75,7 @@ macro_rules! route {
             $(
                 $method: $method,
             )*
+            methods: Vec<Method>,
             _phantom: PhantomData<(Req, Res, Err, Cfg, InitErr)>,
         }
 
@@ -65,6 +85,7 @@ macro_rules! route {
                     $(
                         $method: Default::default(),
                     )*
+                    methods: Vec::new(),
                     _phantom: PhantomData,
                 }
             }
@@ -94,10 +115,11 @@ macro_rules! route {
 
             fn new_service(&self, cfg: Self::Config) -> Self::Future {
                 let ($($method), *) = ($(self.$method.new_service(cfg.clone())), *);
+                let methods = Methods::new(self.methods.clone());
 
                 async move {
                     let ($($method), *) = ($($method.await?), *);
-                    Ok(RouteService { $($method), * })
+                    Ok(RouteService { $($method, ) * methods })
                 }
             }
         }
@@ -153,11 +175,17 @@ macro_rules! route {
         where
             F1: ServiceFactory<Req, Response = Res, Error = Err, Config = Cfg, InitError = InitErr>,
         {
+            let mut methods = self.methods;
+            if !methods.contains(&Method::$method_ty) {
+                methods.push(Method::$method_ty);
+            }
+
             Route {
                 $method_ty: factory.map_err(RouteError::Service),
                 $(
                     $untouched_method: self.$untouched_method,
                 )*
+                methods,
                 _phantom: PhantomData
             }
         }
@@ -168,14 +196,16 @@ macro_rules! route_service {
     ($($method: ident), *) => {
         #[allow(non_camel_case_types)]
         pub struct RouteService<$($method), *> {
-            $($method: $method), *
+            $($method: $method,) *
+            methods: Methods,
         }
 
         impl<ReqB, Res, Err, $($method), *> Service<Request<ReqB>> for RouteService<$($method), *>
         where
              $(
                 $method: Service<Request<ReqB>, Response = Res, Error = RouteError<Err>>
-             ), *
+             ), *,
+             Res: RouteResponse,
         {
             type Response = Res;
             type Error = RouteError<Err>;
@@ -196,11 +226,21 @@ macro_rules! route_service {
             #[inline]
             fn call(&self, req: Request<ReqB>) -> Self::Future<'_> {
                 async move {
-                    match *req.method() {
-                        $(
-                            Method::$method => self.$method.call(req).await,
-                        ) *
-                        _ => Err(RouteError::MethodNotAllowed),
+                    if self.methods.registered.contains(req.method()) {
+                        match *req.method() {
+                            $(
+                                Method::$method => self.$method.call(req).await,
+                            ) *
+                            _ => unreachable!("custom method can not be registered to Route"),
+                        }
+                    } else if *req.method() == Method::HEAD && self.methods.registered.contains(&Method::GET) {
+                        let mut res = self.GET.call(req).await?;
+                        res.remove_body();
+                        Ok(res)
+                    } else if *req.method() == Method::OPTIONS {
+                        Ok(Res::with_allow(StatusCode::OK, self.methods.allow.clone()))
+                    } else {
+                        Ok(Res::with_allow(StatusCode::METHOD_NOT_ALLOWED, self.methods.allow.clone()))
                     }
                 }
             }
@@ -222,10 +262,86 @@ route!(GET, POST, PUT, DELETE, HEAD, OPTIONS, CONNECT, PATCH, TRACE; Req, Req, R
 
 route_service!(GET, POST, PUT, DELETE, HEAD, OPTIONS, CONNECT, PATCH, TRACE);
 
+// registered methods of a RouteService and value of Allow header.
+struct Methods {
+    registered: Vec<Method>,
+    allow: HeaderValue,
+}
+
+impl Methods {
+    fn new(registered: Vec<Method>) -> Self {
+        // HEAD and OPTIONS are implicitly allowed. see RouteService::call.
+        let mut allow = String::new();
+
+        for method in [
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::DELETE,
+            Method::HEAD,
+            Method::OPTIONS,
+            Method::CONNECT,
+            Method::PATCH,
+            Method::TRACE,
+        ] {
+            let allowed = registered.contains(&method)
+                || method == Method::OPTIONS
+                || (method == Method::HEAD && registered.contains(&Method::GET));
+
+            if allowed {
+                if !allow.is_empty() {
+                    allow.push_str(", ");
+                }
+                allow.push_str(method.as_str());
+            }
+        }
+
+        Self {
+            registered,
+            allow: HeaderValue::from_str(&allow).unwrap(),
+        }
+    }
+}
+
+/// Response type of services registered to [Route].
+///
+/// Route uses it for answering `OPTIONS` and `405 Method Not Allowed` with `Allow` header and
+/// for removing body from `GET` service's response when answering `HEAD` request.
+///
+/// This is a bound of Route service. Response types other than `Response<ResponseBody<B>>` must
+/// implement it to be used with Route.
+pub trait RouteResponse {
+    /// Construct a response with empty body, given status code and `Allow` header.
+    fn with_allow(status: StatusCode, allow: HeaderValue) -> Self;
+
+    /// Remove response body. Headers describing the body (e.g. `Content-Length`) are kept.
+    fn remove_body(&mut self);
+}
+
+impl<B> RouteResponse for Response<ResponseBody<B>> {
+    fn with_allow(status: StatusCode, allow: HeaderValue) -> Self {
+        let mut res = Response::new(ResponseBody::Bytes { bytes: Bytes::new() });
+        *res.status_mut() = status;
+        res.headers_mut().insert(ALLOW, allow);
+        res
+    }
+
+    fn remove_body(&mut self) {
+        if let ResponseBody::Bytes { ref bytes } = *self.body() {
+            if !self.headers().contains_key(CONTENT_LENGTH) {
+                let len = bytes.len();
+                self.headers_mut().insert(CONTENT_LENGTH, HeaderValue::from(len));
+            }
+        }
+        *self.body_mut() = ResponseBody::None;
+    }
+}
+
 /// Error type of Route service.
+///
+/// Request with method not allowed is answered with `405 Method Not Allowed` response instead
+/// of an error. See [RouteResponse].
 pub enum RouteError<E> {
-    /// Method is not allowed for route service.
-    MethodNotAllowed,
     /// Error type of the inner service.
     Service(E),
 }
@@ -233,7 +349,6 @@ pub enum RouteError<E> {
 impl<E: fmt::Debug> fmt::Debug for RouteError<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
-            Self::MethodNotAllowed => write!(f, "MethodNotAllowed"),
             Self::Service(ref e) => write!(f, "{:?}", e),
         }
     }
@@ -242,7 +357,6 @@ impl<E: fmt::Debug> fmt::Debug for RouteError<E> {
 impl<E: fmt::Display> fmt::Display for RouteError<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
-            Self::MethodNotAllowed => write!(f, "MethodNotAllowed"),
             Self::Service(ref e) => write!(f, "{}", e),
         }
     }
@@ -250,6 +364,24 @@ impl<E: fmt::Display> fmt::Display for RouteError<E> {
 
 impl<E> error::Error for RouteError<E> where E: fmt::Debug + fmt::Display {}
 
+impl<Req, B, E> ResponseError<Req, Response<ResponseBody<B>>> for RouteError<E>
+where
+    E: ResponseError<Req, Response<ResponseBody<B>>>,
+{
+    fn response_error(&mut self, req: &mut Req) -> Response<ResponseBody<B>> {
+        match *self {
+            Self::Service(ref mut e) => e.response_error(req),
+        }
+    }
+
+    fn is_not_matched(&self) -> bool {
+        match *self {
+            Self::Service(ref e) => e.is_not_matched(),
+        }
+    }
+}
+
+// placeholder of unregistered method. RouteService never calls it.
 #[doc(hidden)]
 pub struct MethodNotAllowed<Res, Err, Cfg, InitErr>(PhantomData<(Res, Err, Cfg, InitErr)>);
 
@@ -297,7 +429,7 @@ impl<Req, Res, Err, Cfg, InitErr> Service<Req> for MethodNotAllowed<Res, Err, Cf
 
     #[inline]
     fn call(&self, _: Req) -> Self::Future<'_> {
-        ready(Err(RouteError::MethodNotAllowed))
+        unreachable!("unregistered method is answered by RouteService")
     }
 }
 
@@ -308,13 +440,10 @@ mod test {
     use std::convert::Infallible;
     use xitca_service::fn_service;
 
-    use crate::{
-        body::{RequestBody, ResponseBody},
-        http::Response,
-    };
+    use crate::body::RequestBody;
 
     async fn index(_: Request<RequestBody>) -> Result<Response<ResponseBody>, Infallible> {
-        Ok(Response::new(ResponseBody::None))
+        Ok(Response::new(Bytes::from_static(b"index").into()))
     }
 
     #[tokio::test]
@@ -333,7 +462,21 @@ mod test {
 
         let mut req = Request::new(RequestBody::None);
         *req.method_mut() = Method::PUT;
-        let err = service.call(req).await.err().unwrap();
-        assert!(matches!(err, RouteError::MethodNotAllowed));
+        let res = service.call(req).await.ok().unwrap();
+        assert_eq!(res.status().as_u16(), 405);
+        assert_eq!(res.headers().get(ALLOW).unwrap(), "GET, POST, HEAD, OPTIONS");
+
+        let mut req = Request::new(RequestBody::None);
+        *req.method_mut() = Method::HEAD;
+        let res = service.call(req).await.ok().unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), "5");
+        assert!(matches!(res.body(), ResponseBody::None));
+
+        let mut req = Request::new(RequestBody::None);
+        *req.method_mut() = Method::OPTIONS;
+        let res = service.call(req).await.ok().unwrap();
+        assert_eq!(res.status().as_u16(), 200);
+        assert_eq!(res.headers().get(ALLOW).unwrap(), "GET, POST, HEAD, OPTIONS");
     }
 }
@@ -1,19 +1,237 @@
 use std::{
+    borrow::Cow,
     collections::HashMap,
     error, fmt,
     future::{ready, Future, Ready},
+    pin::Pin,
+    sync::{Arc, Mutex},
 };
 
 use matchit::{MatchError, Node};
-use xitca_service::{Service, ServiceFactory, ServiceFactoryExt, ServiceFactoryObject, ServiceObject};
+use xitca_service::{Service, ServiceFactory, ServiceFactoryExt, ServiceFactoryObject, ServiceObject, Transform};
 
-use crate::http::Request;
+use crate::{
+    body::ResponseBody,
+    bytes::Bytes,
+    http::{
+        header::{HeaderValue, LOCATION},
+        uri::PathAndQuery,
+        Request, Response, StatusCode, Uri,
+    },
+    response::ResponseError,
+};
 
 /// Simple router for matching on [Request]'s path and call according service.
 pub struct Router<Req, Res, Err, Cfg, InitErr> {
     routes: HashMap<&'static str, ServiceFactoryObject<Req, Res, Err, Cfg, InitErr>>,
+    names: HashMap<&'static str, &'static str>,
+    fallback: Option<ServiceFactoryObject<Req, Res, Err, Cfg, InitErr>>,
+    path_config: PathConfig,
+}
+
+/// Handling of request path that does not match any route but a route exists at the same path
+/// with/without a trailing slash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Path must match route exactly.
+    Strict,
+    /// Request's path is rewritten to the matching route's path and passed to it's service.
+    Rewrite,
+    /// Request is rejected with [RouterError::Redirect] pointing to the matching route's path.
+    Redirect,
+}
+
+#[derive(Clone, Copy)]
+struct PathConfig {
+    trailing_slash: TrailingSlash,
+    merge_slashes: bool,
+    percent_decode: bool,
+}
+
+impl Default for PathConfig {
+    fn default() -> Self {
+        Self {
+            trailing_slash: TrailingSlash::Strict,
+            merge_slashes: false,
+            percent_decode: false,
+        }
+    }
+}
+
+/// The path pattern a request matched in [Router].
+///
+/// It's inserted into request's extensions before calling the matched service and can be used
+/// for observing which route handled a request. (e.g. labelling metrics by route pattern)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MatchedPath(pub &'static str);
+
+impl MatchedPath {
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+// Slot inserted into request's extensions by middlewares wrapping Router. Router writes matched
+// path into it so it can be observed after the call no matter how the response is constructed.
+#[derive(Clone, Default)]
+pub(crate) struct MatchedPathSlot(Arc<Mutex<Option<MatchedPath>>>);
+
+impl MatchedPathSlot {
+    pub(crate) fn get(&self) -> Option<MatchedPath> {
+        *self.0.lock().unwrap()
+    }
+
+    fn set(&self, path: MatchedPath) {
+        *self.0.lock().unwrap() = Some(path);
+    }
+}
+
+/// Url generator for named routes of [Router].
+///
+/// It's inserted into request's extensions by Router when any route is inserted with
+/// [Router::insert_named].
+#[derive(Clone)]
+pub struct UrlFor {
+    names: Arc<HashMap<&'static str, &'static str>>,
+    prefix: String,
+}
+
+impl UrlFor {
+    fn new(names: HashMap<&'static str, &'static str>) -> Self {
+        Self {
+            names: Arc::new(names),
+            prefix: String::new(),
+        }
+    }
+
+    /// Prepend given path prefix to generated url paths.
+    ///
+    /// This is useful when Router is mounted under a path prefix that is stripped from
+    /// request's path before it reaches Router.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix.insert_str(0, prefix.trim_end_matches('/'));
+        self
+    }
+
+    /// Generate url path of named route with given params.
+    ///
+    /// Params are percent-encoded. Catch-all param (`*param`) can contain `/` that is kept as is.
+    pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlForError> {
+        let path = self
+            .names
+            .get(name)
+            .ok_or_else(|| UrlForError::UnknownRoute(name.to_owned()))?;
+
+        let mut url = String::with_capacity(self.prefix.len() + path.len());
+        url.push_str(&self.prefix);
+        let mut rest = *path;
+
+        while let Some(idx) = rest.find(|c| c == ':' || c == '*') {
+            url.push_str(&rest[..idx]);
+
+            let catch_all = rest.as_bytes()[idx] == b'*';
+            rest = &rest[idx + 1..];
+
+            let end = if catch_all {
+                rest.len()
+            } else {
+                rest.find('/').unwrap_or(rest.len())
+            };
+            let param = &rest[..end];
+            rest = &rest[end..];
+
+            let value = params
+                .iter()
+                .find(|(k, _)| *k == param)
+                .map(|(_, v)| *v)
+                .ok_or_else(|| UrlForError::MissingParam(param.to_owned()))?;
+
+            encode_into(&mut url, value, |b| is_pchar(b) || (catch_all && b == b'/'));
+        }
+
+        url.push_str(rest);
+
+        Ok(url)
+    }
+
+    /// Generate url path of named route with given params and query.
+    ///
+    /// Query pairs are percent-encoded.
+    pub fn url_for_with_query(
+        &self,
+        name: &str,
+        params: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, UrlForError> {
+        let mut url = self.url_for(name, params)?;
+
+        for (i, (k, v)) in query.iter().enumerate() {
+            url.push(if i == 0 { '?' } else { '&' });
+            encode_into(&mut url, k, is_unreserved);
+            url.push('=');
+            encode_into(&mut url, v, is_unreserved);
+        }
+
+        Ok(url)
+    }
+}
+
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~')
+}
+
+// unreserved, sub-delims and ':' '@' are allowed in path segment. (RFC 3986 3.3)
+fn is_pchar(b: u8) -> bool {
+    is_unreserved(b)
+        || matches!(
+            b,
+            b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=' | b':' | b'@'
+        )
+}
+
+fn encode_into(buf: &mut String, value: &str, allowed: impl Fn(u8) -> bool) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    for &b in value.as_bytes() {
+        if allowed(b) {
+            buf.push(b as char);
+        } else {
+            buf.push('%');
+            buf.push(HEX[(b >> 4) as usize] as char);
+            buf.push(HEX[(b & 0xf) as usize] as char);
+        }
+    }
+}
+
+/// Error type of [UrlFor].
+pub enum UrlForError {
+    /// No route is inserted with given name.
+    UnknownRoute(String),
+    /// Param required by route's path is not provided.
+    MissingParam(String),
 }
 
+impl fmt::Debug for UrlForError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::UnknownRoute(ref name) => write!(f, "UnknownRoute({:?})", name),
+            Self::MissingParam(ref param) => write!(f, "MissingParam({:?})", param),
+        }
+    }
+}
+
+impl fmt::Display for UrlForError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::UnknownRoute(ref name) => write!(f, "Route named {} is not found", name),
+            Self::MissingParam(ref param) => write!(f, "Param {} is missing", param),
+        }
+    }
+}
+
+impl error::Error for UrlForError {}
+
 /// Error type of Router service.
 pub enum RouterError<E> {
     /// Error occur on matching service.
@@ -21,6 +239,10 @@ pub enum RouterError<E> {
     /// [MatchError::tsr] method can be used to hint if a route exists at the same path
     /// with/without a trailing slash.
     MatchError(MatchError),
+    /// Request should be redirected to given uri.
+    ///
+    /// Only emitted when [TrailingSlash::Redirect] is enabled for Router.
+    Redirect(Uri),
     /// Error type of the inner service.
     Service(E),
 }
@@ -29,6 +251,7 @@ impl<E: fmt::Debug> fmt::Debug for RouterError<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Self::MatchError(ref e) => write!(f, "{:?}", e),
+            Self::Redirect(ref uri) => write!(f, "Redirect({:?})", uri),
             Self::Service(ref e) => write!(f, "{:?}", e),
         }
     }
@@ -38,6 +261,7 @@ impl<E: fmt::Display> fmt::Display for RouterError<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Self::MatchError(ref e) => write!(f, "{}", e),
+            Self::Redirect(ref uri) => write!(f, "Redirect to {}", uri),
             Self::Service(ref e) => write!(f, "{}", e),
         }
     }
@@ -45,6 +269,38 @@ impl<E: fmt::Display> fmt::Display for RouterError<E> {
 
 impl<E> error::Error for RouterError<E> where E: fmt::Debug + fmt::Display {}
 
+impl<Req, B, E> ResponseError<Req, Response<ResponseBody<B>>> for RouterError<E>
+where
+    E: ResponseError<Req, Response<ResponseBody<B>>>,
+{
+    fn response_error(&mut self, req: &mut Req) -> Response<ResponseBody<B>> {
+        match *self {
+            Self::MatchError(_) => {
+                let mut res = Response::new(Bytes::new().into());
+                *res.status_mut() = StatusCode::NOT_FOUND;
+                res
+            }
+            Self::Redirect(ref uri) => {
+                let mut res = Response::new(Bytes::new().into());
+                *res.status_mut() = StatusCode::PERMANENT_REDIRECT;
+                if let Ok(location) = HeaderValue::from_str(&uri.to_string()) {
+                    res.headers_mut().insert(LOCATION, location);
+                }
+                res
+            }
+            Self::Service(ref mut e) => e.response_error(req),
+        }
+    }
+
+    fn is_not_matched(&self) -> bool {
+        match *self {
+            Self::MatchError(_) => true,
+            Self::Redirect(_) => false,
+            Self::Service(ref e) => e.is_not_matched(),
+        }
+    }
+}
+
 impl<Req, Res, Err, Cfg, InitErr> Default for Router<Req, Res, Err, Cfg, InitErr> {
     fn default() -> Self {
         Self::new()
@@ -53,7 +309,40 @@ impl<Req, Res, Err, Cfg, InitErr> Default for Router<Req, Res, Err, Cfg, InitErr
 
 impl<Req, Res, Err, Cfg, InitErr> Router<Req, Res, Err, Cfg, InitErr> {
     pub fn new() -> Self {
-        Self { routes: HashMap::new() }
+        Self {
+            routes: HashMap::new(),
+            names: HashMap::new(),
+            fallback: None,
+            path_config: PathConfig::default(),
+        }
+    }
+
+    /// Set handling of trailing slash mismatch.
+    ///
+    /// Default to [TrailingSlash::Strict].
+    pub fn trailing_slash(mut self, mode: TrailingSlash) -> Self {
+        self.path_config.trailing_slash = mode;
+        self
+    }
+
+    /// Collapse consecutive slashes of request path into one before matching.
+    /// Request's uri would be rewritten to the collapsed path.
+    ///
+    /// Default to false.
+    pub fn merge_slashes(mut self, value: bool) -> Self {
+        self.path_config.merge_slashes = value;
+        self
+    }
+
+    /// Percent-decode request path before matching.
+    ///
+    /// Encoded slash (`%2F`) is not decoded so it can not alter path segments.
+    /// Request's uri stays encoded.
+    ///
+    /// Default to false.
+    pub fn percent_decode(mut self, value: bool) -> Self {
+        self.path_config.percent_decode = value;
+        self
     }
 
     /// Insert a new service factory to given path.
@@ -71,6 +360,108 @@ impl<Req, Res, Err, Cfg, InitErr> Router<Req, Res, Err, Cfg, InitErr> {
         assert!(self.routes.insert(path, factory.into_object()).is_none());
         self
     }
+
+    /// Insert a new service factory to given path with a name.
+    ///
+    /// Url of named route can be generated with [UrlFor] which is inserted into request's
+    /// extensions by Router.
+    ///
+    /// # Panic:
+    ///
+    /// When multiple services inserted with the same path or the same name.
+    pub fn insert_named<F>(mut self, name: &'static str, path: &'static str, factory: F) -> Self
+    where
+        F: ServiceFactory<Req, Response = Res, Error = Err, Config = Cfg, InitError = InitErr> + 'static,
+        F::Service: 'static,
+        F::Future: 'static,
+        Req: 'static,
+    {
+        assert!(self.names.insert(name, path).is_none());
+        self.insert(path, factory)
+    }
+
+    /// Set a service factory for requests that do not match any route.
+    ///
+    /// Without fallback Router would emit [RouterError::MatchError] for these requests.
+    pub fn fallback<F>(mut self, factory: F) -> Self
+    where
+        F: ServiceFactory<Req, Response = Res, Error = Err, Config = Cfg, InitError = InitErr> + 'static,
+        F::Service: 'static,
+        F::Future: 'static,
+        Req: 'static,
+    {
+        self.fallback = Some(factory.into_object());
+        self
+    }
+
+    /// Apply given transform to all services inserted to router **before** this call.
+    ///
+    /// Combined with [Router::merge] transform can be applied to a group of routes:
+    /// ```rust,ignore
+    /// let admin = Router::new()
+    ///     .insert("/admin/users", get(fn_service(users)))
+    ///     .insert("/admin/posts", get(fn_service(posts)))
+    ///     .transform(auth);
+    ///
+    /// let router = Router::new().insert("/", get(fn_service(index))).merge(admin);
+    /// ```
+    ///
+    /// For a single route the transform can be applied to it's service factory directly with
+    /// [ServiceFactoryExt::transform] before inserting it to router.
+    pub fn transform<T>(mut self, transform: T) -> Self
+    where
+        T: Transform<ServiceObject<Req, Res, Err>, Req, Response = Res, Error = Err> + 'static,
+        T::Transform: 'static,
+        T::Future: 'static,
+        InitErr: From<T::InitError> + 'static,
+        Req: 'static,
+        Res: 'static,
+        Err: 'static,
+        Cfg: 'static,
+    {
+        self.routes = self
+            .routes
+            .into_iter()
+            .map(|(path, obj)| (path, RouteObject(obj).transform(transform.clone()).into_object()))
+            .collect();
+        self.fallback = self
+            .fallback
+            .map(|obj| RouteObject(obj).transform(transform).into_object());
+        self
+    }
+
+    /// Insert all services of given router to current router.
+    ///
+    /// Path handling options and fallback of given router are ignored and current router's are used.
+    ///
+    /// # Panic:
+    ///
+    /// When both routers have service inserted with the same path or name.
+    pub fn merge(mut self, other: Self) -> Self {
+        for (path, obj) in other.routes {
+            assert!(self.routes.insert(path, obj).is_none());
+        }
+        for (name, path) in other.names {
+            assert!(self.names.insert(name, path).is_none());
+        }
+        self
+    }
+}
+
+// ServiceFactory wrapper for boxed route so it can be transformed.
+struct RouteObject<Req, Res, Err, Cfg, InitErr>(ServiceFactoryObject<Req, Res, Err, Cfg, InitErr>);
+
+impl<Req, Res, Err, Cfg, InitErr> ServiceFactory<Req> for RouteObject<Req, Res, Err, Cfg, InitErr> {
+    type Response = Res;
+    type Error = Err;
+    type Config = Cfg;
+    type Service = ServiceObject<Req, Res, Err>;
+    type InitError = InitErr;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Service, Self::InitError>>>>;
+
+    fn new_service(&self, cfg: Self::Config) -> Self::Future {
+        self.0.new_service(cfg)
+    }
 }
 
 impl<ReqB, Res, Err, Cfg, InitErr> ServiceFactory<Request<ReqB>> for Router<Request<ReqB>, Res, Err, Cfg, InitErr>
@@ -91,27 +482,48 @@ where
             .map(|(path, obj)| (*path, obj.new_service(cfg.clone())))
             .collect::<Vec<_>>();
 
+        let fallback = self.fallback.as_ref().map(|obj| obj.new_service(cfg));
+
+        let path_config = self.path_config;
+        let url_for = (!self.names.is_empty()).then(|| UrlFor::new(self.names.clone()));
+
         async move {
             let mut routes = matchit::Node::new();
 
             for (path, fut) in futs {
                 let service = fut.await?;
-                routes.insert(path, service).unwrap();
+                routes.insert(path, (path, service)).unwrap();
             }
 
-            Ok(RouterService { routes })
+            let fallback = match fallback {
+                Some(fut) => Some(fut.await?),
+                None => None,
+            };
+
+            Ok(RouterService {
+                routes,
+                fallback,
+                path_config,
+                url_for,
+            })
         }
     }
 }
 
 pub struct RouterService<Req, Res, Err> {
-    routes: Node<ServiceObject<Req, Res, Err>>,
+    routes: Node<(&'static str, ServiceObject<Req, Res, Err>)>,
+    fallback: Option<ServiceObject<Req, Res, Err>>,
+    path_config: PathConfig,
+    url_for: Option<UrlFor>,
 }
 
 impl<Req, Res, Err> Clone for RouterService<Req, Res, Err> {
     fn clone(&self) -> Self {
         Self {
             routes: self.routes.clone(),
+            fallback: self.fallback.clone(),
+            path_config: self.path_config,
+            url_for: self.url_for.clone(),
         }
     }
 }
@@ -134,11 +546,333 @@ impl<ReqB, Res, Err> Service<Request<ReqB>> for RouterService<Request<ReqB>, Res
     }
 
     #[inline]
-    fn call(&self, req: Request<ReqB>) -> Self::Future<'_> {
+    fn call(&self, mut req: Request<ReqB>) -> Self::Future<'_> {
         async move {
-            let service = self.routes.at(req.uri().path()).map_err(RouterError::MatchError)?;
+            if let Some(ref url_for) = self.url_for {
+                req.extensions_mut().insert(url_for.clone());
+            }
+
+            let config = &self.path_config;
+
+            // owned path is normalized and would be written back to request's uri.
+            let mut path = match config.merge_slashes {
+                true => merge_slashes(req.uri().path()),
+                false => Cow::Borrowed(req.uri().path()),
+            };
+
+            let res = self.routes.at(&decode(&path, config.percent_decode)).map(|m| m.value);
+
+            let res = match res {
+                Err(e) if e.tsr() && config.trailing_slash != TrailingSlash::Strict => {
+                    let toggled = toggle_trailing_slash(&path);
+
+                    if config.trailing_slash == TrailingSlash::Redirect {
+                        let uri = rewrite_uri(req.uri(), &toggled).ok_or(RouterError::MatchError(e))?;
+                        return Err(RouterError::Redirect(uri));
+                    }
+
+                    let res = self
+                        .routes
+                        .at(&decode(&toggled, config.percent_decode))
+                        .map(|m| m.value);
+
+                    if res.is_ok() {
+                        path = Cow::Owned(toggled);
+                    }
+
+                    res
+                }
+                res => res,
+            };
+
+            let (matched, service) = match res {
+                Ok(value) => value,
+                Err(e) => match self.fallback {
+                    Some(ref fallback) => return fallback.call(req).await.map_err(RouterError::Service),
+                    None => return Err(RouterError::MatchError(e)),
+                },
+            };
+
+            if let Cow::Owned(path) = path {
+                if let Some(uri) = rewrite_uri(req.uri(), &path) {
+                    *req.uri_mut() = uri;
+                }
+            }
+
+            if let Some(slot) = req.extensions().get::<MatchedPathSlot>() {
+                slot.set(MatchedPath(*matched));
+            }
+
+            req.extensions_mut().insert(MatchedPath(*matched));
+
+            service.call(req).await.map_err(RouterError::Service)
+        }
+    }
+}
+
+fn merge_slashes(path: &str) -> Cow<'_, str> {
+    if !path.contains("//") {
+        return Cow::Borrowed(path);
+    }
+
+    let mut res = String::with_capacity(path.len());
+    let mut prev = '\0';
+    for c in path.chars() {
+        if c != '/' || prev != '/' {
+            res.push(c);
+        }
+        prev = c;
+    }
+    Cow::Owned(res)
+}
+
+fn toggle_trailing_slash(path: &str) -> String {
+    match path.strip_suffix('/') {
+        Some(path) => path.to_owned(),
+        None => format!("{}/", path),
+    }
+}
+
+// percent decode path. encoded slash and invalid utf-8 sequence are left as is.
+fn decode(path: &str, enable: bool) -> Cow<'_, str> {
+    if !enable || !path.contains('%') {
+        return Cow::Borrowed(path);
+    }
+
+    fn hex(b: u8) -> Option<u8> {
+        match b {
+            b'0'..=b'9' => Some(b - b'0'),
+            b'a'..=b'f' => Some(b - b'a' + 10),
+            b'A'..=b'F' => Some(b - b'A' + 10),
+            _ => None,
+        }
+    }
+
+    let bytes = path.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(h), Some(l)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                let b = h << 4 | l;
+                if b != b'/' {
+                    res.push(b);
+                    i += 3;
+                    continue;
+                }
+            }
+        }
+        res.push(bytes[i]);
+        i += 1;
+    }
+
+    match String::from_utf8(res) {
+        Ok(res) => Cow::Owned(res),
+        Err(_) => Cow::Borrowed(path),
+    }
+}
+
+fn rewrite_uri(uri: &Uri, path: &str) -> Option<Uri> {
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_owned(),
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(PathAndQuery::from_maybe_shared(path_and_query).ok()?);
+    Uri::from_parts(parts).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::convert::Infallible;
+
+    use xitca_service::fn_service;
+
+    use crate::body::RequestBody;
+
+    async fn index(req: Request<RequestBody>) -> Result<Response<ResponseBody>, Infallible> {
+        let mut res = Response::new(ResponseBody::None);
+        res.headers_mut()
+            .insert("x-path", HeaderValue::from_str(req.uri().path()).unwrap());
+        Ok(res)
+    }
+
+    fn req(path: &'static str) -> Request<RequestBody> {
+        let mut req = Request::new(RequestBody::None);
+        *req.uri_mut() = Uri::from_static(path);
+        req
+    }
+
+    #[derive(Clone)]
+    struct Marker;
+
+    impl<S> Transform<S, Request<RequestBody>> for Marker
+    where
+        S: Service<Request<RequestBody>, Response = Response<ResponseBody>>,
+    {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Transform = MarkerService<S>;
+        type InitError = ();
+        type Future = impl Future<Output = Result<Self::Transform, Self::InitError>>;
+
+        fn new_transform(&self, service: S) -> Self::Future {
+            async { Ok(MarkerService(service)) }
+        }
+    }
+
+    struct MarkerService<S>(S);
+
+    impl<S> Service<Request<RequestBody>> for MarkerService<S>
+    where
+        S: Service<Request<RequestBody>, Response = Response<ResponseBody>>,
+    {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Ready<'f>
+        where
+            Self: 'f,
+        = S::Ready<'f>;
+        type Future<'f>
+        where
+            Self: 'f,
+        = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+        fn ready(&self) -> Self::Ready<'_> {
+            self.0.ready()
+        }
+
+        fn call(&self, req: Request<RequestBody>) -> Self::Future<'_> {
+            async move {
+                let mut res = self.0.call(req).await?;
+                res.headers_mut().insert("x-marker", HeaderValue::from_static("1"));
+                Ok(res)
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn fallback() {
+        async fn fallback(_: Request<RequestBody>) -> Result<Response<ResponseBody>, Infallible> {
+            let mut res = Response::new(ResponseBody::None);
+            res.headers_mut().insert("x-path", HeaderValue::from_static("fallback"));
+            Ok(res)
+        }
+
+        let service = Router::new()
+            .insert("/foo", fn_service(index))
+            .fallback(fn_service(fallback))
+            .new_service(())
+            .await
+            .ok()
+            .unwrap();
+
+        let res = service.call(req("/foo")).await.ok().unwrap();
+        assert_eq!(res.headers().get("x-path").unwrap(), "/foo");
+
+        let res = service.call(req("/bar")).await.ok().unwrap();
+        assert_eq!(res.headers().get("x-path").unwrap(), "fallback");
+    }
+
+    #[tokio::test]
+    async fn group_transform() {
+        let admin = Router::new()
+            .insert("/admin/foo", fn_service(index))
+            .insert("/admin/bar", fn_service(index))
+            .transform(Marker);
+
+        let service = Router::new()
+            .insert("/", fn_service(index))
+            .merge(admin)
+            .new_service(())
+            .await
+            .ok()
+            .unwrap();
+
+        let res = service.call(req("/")).await.ok().unwrap();
+        assert!(res.headers().get("x-marker").is_none());
+
+        for path in ["/admin/foo", "/admin/bar"] {
+            let res = service.call(req(path)).await.ok().unwrap();
+            assert!(res.headers().get("x-marker").is_some());
+        }
+    }
+
+    #[test]
+    fn url_for() {
+        let mut names = HashMap::new();
+        names.insert("user", "/users/:id/posts/:post");
+        names.insert("static", "/static/*path");
+        let url_for = UrlFor::new(names);
+
+        assert_eq!(
+            url_for.url_for("user", &[("id", "a b/c"), ("post", "1")]).unwrap(),
+            "/users/a%20b%2Fc/posts/1"
+        );
+        assert_eq!(
+            url_for.url_for("static", &[("path", "css/main.css")]).unwrap(),
+            "/static/css/main.css"
+        );
+        assert_eq!(
+            url_for
+                .url_for_with_query("user", &[("id", "1"), ("post", "2")], &[("q", "a&b"), ("page", "1")])
+                .unwrap(),
+            "/users/1/posts/2?q=a%26b&page=1"
+        );
+
+        assert!(matches!(
+            url_for.url_for("user", &[("id", "1")]),
+            Err(UrlForError::MissingParam(p)) if p == "post"
+        ));
+        assert!(matches!(url_for.url_for("foo", &[]), Err(UrlForError::UnknownRoute(_))));
+
+        let url_for = url_for.prefix("/api/").prefix("/v1");
+        assert_eq!(
+            url_for.url_for("static", &[("path", "main.css")]).unwrap(),
+            "/v1/api/static/main.css"
+        );
+    }
+
+    #[test]
+    fn path_helpers() {
+        assert_eq!(merge_slashes("//foo///bar/"), "/foo/bar/");
+        assert_eq!(toggle_trailing_slash("/foo/"), "/foo");
+        assert_eq!(toggle_trailing_slash("/foo"), "/foo/");
+        assert_eq!(decode("/f%6Fo%2Fbar%", true), "/foo%2Fbar%");
+        assert_eq!(decode("/f%6Fo", false), "/f%6Fo");
+    }
+
+    #[tokio::test]
+    async fn normalize() {
+        let service = Router::new()
+            .insert("/foo/bar", fn_service(index))
+            .trailing_slash(TrailingSlash::Rewrite)
+            .merge_slashes(true)
+            .percent_decode(true)
+            .new_service(())
+            .await
+            .ok()
+            .unwrap();
+
+        for path in ["/foo/bar", "/foo/bar/", "//foo//bar", "/f%6Fo/bar/?q=1"] {
+            let res = service.call(req(path)).await.ok().unwrap();
+            assert!(res.headers().get("x-path").unwrap().to_str().unwrap().ends_with("bar"));
+        }
+
+        let service = Router::new()
+            .insert("/foo", fn_service(index))
+            .trailing_slash(TrailingSlash::Redirect)
+            .new_service(())
+            .await
+            .ok()
+            .unwrap();
 
-            service.value.call(req).await.map_err(RouterError::Service)
+        match service.call(req("/foo/?q=1")).await.err().unwrap() {
+            RouterError::Redirect(uri) => assert_eq!(uri, "/foo?q=1"),
+            _ => panic!("redirect error is expected"),
         }
     }
 }
@@ -1,11 +1,22 @@
+use std::{cmp, time::Duration};
+
+use tokio::time::sleep;
 use xitca_io::net::TcpStream;
 
 use crate::http::Version;
 
+/// Connection preface of Http/2. Clients with prior knowledge start connection with it.
+pub(crate) const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
 /// A helper trait for get a protocol from certain types.
 pub trait AsVersion {
     fn as_version(&self) -> Version;
 
+    /// Return true when connection is secured with tls.
+    fn is_tls(&self) -> bool {
+        false
+    }
+
     fn from_alpn<B: AsRef<[u8]>>(proto: B) -> Version {
         if proto.as_ref().windows(2).any(|window| window == b"h2") {
             Version::HTTP_2
@@ -19,7 +30,7 @@ impl AsVersion for xitca_io::net::Stream {
     #[inline]
     fn as_version(&self) -> Version {
         match *self {
-            Self::Tcp(ref tcp) => tcp.as_version(),
+            Self::Tcp(ref tcp, _) => tcp.as_version(),
             #[cfg(unix)]
             Self::Unix(..) => Version::HTTP_11,
             #[cfg(feature = "http3")]
@@ -34,3 +45,65 @@ impl AsVersion for TcpStream {
         Version::HTTP_11
     }
 }
+
+#[cfg(unix)]
+impl AsVersion for xitca_io::net::UnixStream {
+    #[inline]
+    fn as_version(&self) -> Version {
+        Version::HTTP_11
+    }
+}
+
+/// Peek into the start of connection and check if it's Http/2 connection preface.
+///
+/// Nothing is consumed from the stream. Io error and closed connection are treated as not
+/// matching and left to the dispatcher to observe.
+///
+/// Caller must bound the returned future with a timeout as a client can send partial preface
+/// and stall.
+pub(crate) async fn peek_h2_preface(stream: &TcpStream) -> bool {
+    const MAX_BACKOFF: Duration = Duration::from_millis(64);
+
+    let mut buf = [0; H2_PREFACE.len()];
+    let mut backoff = Duration::from_millis(1);
+
+    loop {
+        match stream.peek(&mut buf).await {
+            Ok(0) | Err(_) => return false,
+            Ok(n) => match match_preface(&buf[..n]) {
+                Some(matched) => return matched,
+                // peek (and readiness of stream) returns immediately when partial preface is
+                // already buffered. back off to wait for the rest of it without spinning.
+                None => {
+                    sleep(backoff).await;
+                    backoff = cmp::min(backoff * 2, MAX_BACKOFF);
+                }
+            },
+        }
+    }
+}
+
+// None when bytes are a prefix of the preface and more are needed to make a decision.
+fn match_preface(buf: &[u8]) -> Option<bool> {
+    if !H2_PREFACE.starts_with(buf) {
+        Some(false)
+    } else if buf.len() == H2_PREFACE.len() {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn preface() {
+        assert_eq!(match_preface(H2_PREFACE), Some(true));
+        assert_eq!(match_preface(b"PRI * HTTP/2.0\r\n"), None);
+        assert_eq!(match_preface(b"GET / HTTP/1.1\r\n"), Some(false));
+        assert_eq!(match_preface(b"P"), None);
+        assert_eq!(match_preface(b"POST"), Some(false));
+    }
+}
@@ -33,20 +33,26 @@ impl UdpListener {
     pub fn accept(&self) -> Accept<'_> {
         Accept {
             recv: self.incoming.recv(),
+            local_addr: self.endpoint.local_addr().ok(),
         }
     }
 }
 
 pub struct Accept<'a> {
     recv: Recv<'a, Connecting>,
+    local_addr: Option<SocketAddr>,
 }
 
 impl Future for Accept<'_> {
     type Output = io::Result<UdpStream>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        match ready!(Pin::new(&mut self.get_mut().recv).poll(cx)) {
-            Ok(connecting) => Poll::Ready(Ok(UdpStream { connecting })),
+        let this = self.get_mut();
+        match ready!(Pin::new(&mut this.recv).poll(cx)) {
+            Ok(connecting) => Poll::Ready(Ok(UdpStream {
+                connecting,
+                local_addr: this.local_addr,
+            })),
             Err(_) => Poll::Ready(Err(io::Error::new(
                 io::ErrorKind::BrokenPipe,
                 "quinn endpoint is closed",
@@ -118,6 +124,7 @@ impl Future for Next<'_> {
 /// Naming is to keep consistent with `TcpStream` / `UnixStream`.
 pub struct UdpStream {
     connecting: Connecting,
+    local_addr: Option<SocketAddr>,
 }
 
 impl UdpStream {
@@ -140,4 +147,9 @@ impl UdpStream {
     pub fn peer_addr(&self) -> SocketAddr {
         self.connecting.remote_address()
     }
+
+    /// Get local [`SocketAddr`] of the endpoint self is accepted from.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
 }
@@ -38,7 +38,9 @@ pub mod net {
     pub use tokio::net::{TcpListener, TcpSocket, TcpStream};
 
     #[cfg(unix)]
-    pub use tokio::net::{UnixListener, UnixStream};
+    pub use tokio::net::{unix::SocketAddr as UnixSocketAddr, UnixListener, UnixStream};
+
+    use std::net::SocketAddr;
 
     #[cfg(feature = "http3")]
     pub use crate::h3::*;
@@ -57,13 +59,13 @@ pub mod net {
         pub async fn accept(&self) -> std::io::Result<Stream> {
             match *self {
                 Self::Tcp(ref tcp) => {
-                    let (stream, _) = tcp.accept().await?;
+                    let (stream, addr) = tcp.accept().await?;
 
                     // This two way conversion is to deregister stream from the listener thread's poll
                     // and re-register it to current thread's poll.
                     let stream = stream.into_std()?;
                     let stream = TcpStream::from_std(stream)?;
-                    Ok(Stream::Tcp(stream))
+                    Ok(Stream::Tcp(stream, addr))
                 }
                 #[cfg(feature = "http3")]
                 Self::Udp(ref udp) => {
@@ -72,25 +74,94 @@ pub mod net {
                 }
                 #[cfg(unix)]
                 Self::Unix(ref unix) => {
-                    let (stream, _) = unix.accept().await?;
+                    let (stream, addr) = unix.accept().await?;
 
                     // This two way conversion is to deregister stream from the listener thread's poll
                     // and re-register it to current thread's poll.
                     let stream = stream.into_std()?;
                     let stream = UnixStream::from_std(stream)?;
-                    Ok(Stream::Unix(stream))
+                    Ok(Stream::Unix(stream, addr))
                 }
             }
         }
     }
 
+    /// Ip socket addresses of a connection.
+    ///
+    /// Both methods default to `None` so IO types without ip socket address (e.g. Unix stream)
+    /// can implement it with an empty impl block.
+    pub trait PeerAddr {
+        /// Get remote address of connection.
+        fn peer_addr(&self) -> Option<SocketAddr> {
+            None
+        }
+
+        /// Get local address of connection.
+        fn local_addr(&self) -> Option<SocketAddr> {
+            None
+        }
+    }
+
+    impl PeerAddr for TcpStream {
+        #[inline]
+        fn peer_addr(&self) -> Option<SocketAddr> {
+            TcpStream::peer_addr(self).ok()
+        }
+
+        #[inline]
+        fn local_addr(&self) -> Option<SocketAddr> {
+            TcpStream::local_addr(self).ok()
+        }
+    }
+
+    #[cfg(unix)]
+    impl PeerAddr for UnixStream {}
+
+    impl PeerAddr for Stream {
+        #[inline]
+        fn peer_addr(&self) -> Option<SocketAddr> {
+            Stream::peer_addr(self)
+        }
+
+        #[inline]
+        fn local_addr(&self) -> Option<SocketAddr> {
+            Stream::local_addr(self)
+        }
+    }
+
     /// A collection of stream types of different protocol.
+    ///
+    /// Tcp and Unix variants carry the peer address returned by accepting the stream.
     pub enum Stream {
-        Tcp(TcpStream),
+        Tcp(TcpStream, SocketAddr),
         #[cfg(feature = "http3")]
         Udp(UdpStream),
         #[cfg(unix)]
-        Unix(UnixStream),
+        Unix(UnixStream, UnixSocketAddr),
+    }
+
+    impl Stream {
+        /// Get remote address of stream. Unix stream does not have an ip socket address.
+        pub fn peer_addr(&self) -> Option<SocketAddr> {
+            match *self {
+                Self::Tcp(_, addr) => Some(addr),
+                #[cfg(feature = "http3")]
+                Self::Udp(ref udp) => Some(udp.peer_addr()),
+                #[cfg(unix)]
+                Self::Unix(..) => None,
+            }
+        }
+
+        /// Get local address of stream. Unix stream does not have an ip socket address.
+        pub fn local_addr(&self) -> Option<SocketAddr> {
+            match *self {
+                Self::Tcp(ref tcp, _) => tcp.local_addr().ok(),
+                #[cfg(feature = "http3")]
+                Self::Udp(ref udp) => udp.local_addr(),
+                #[cfg(unix)]
+                Self::Unix(..) => None,
+            }
+        }
     }
 }
 
@@ -4,11 +4,13 @@ use xitca_io::net::TcpSocket;
 
 use crate::net::{AsListener, FromStream};
 use crate::server::{AsServiceFactoryClone, Factory, Server, ServerFuture, ServerFutureInner, ServiceFactoryClone};
+use crate::worker::ConnectionCounter;
 
 pub struct Builder {
     pub(crate) server_threads: usize,
     pub(crate) worker_threads: usize,
     pub(crate) connection_limit: usize,
+    pub(crate) connection_counter: ConnectionCounter,
     pub(crate) worker_max_blocking_threads: usize,
     pub(crate) listeners: HashMap<String, Vec<Box<dyn AsListener>>>,
     pub(crate) factories: HashMap<String, Box<dyn ServiceFactoryClone>>,
@@ -31,6 +33,7 @@ impl Builder {
             server_threads: 1,
             worker_threads: num_cpus::get(),
             connection_limit: 25600,
+            connection_counter: ConnectionCounter::default(),
             worker_max_blocking_threads: 512,
             listeners: HashMap::new(),
             factories: HashMap::new(),
@@ -86,6 +89,14 @@ impl Builder {
         self
     }
 
+    /// Get a counter of living connections of all worker threads.
+    ///
+    /// The counter is updated by workers when connections are accepted and dropped. It can be
+    /// cloned and moved to other threads for observing server load.
+    pub fn connection_counter(&self) -> ConnectionCounter {
+        self.connection_counter.clone()
+    }
+
     /// Set max number of threads for each worker's blocking task thread pool.
     ///
     /// One thread pool is set up **per worker**; not shared across workers.
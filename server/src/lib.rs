@@ -14,6 +14,7 @@ pub mod net;
 
 pub use builder::Builder;
 pub use server::{ServerFuture, ServerHandle};
+pub use worker::ConnectionCounter;
 
 #[cfg(all(not(target_os = "linux"), feature = "io-uring"))]
 compile_error!("io_uring can only be used on linux system");
@@ -21,7 +21,7 @@ impl FromStream for Stream {
 impl FromStream for TcpStream {
     fn from_stream(stream: Stream) -> Self {
         match stream {
-            Stream::Tcp(tcp) => tcp,
+            Stream::Tcp(tcp, _) => tcp,
             _ => unreachable!("Can not be casted to TcpStream"),
         }
     }
@@ -31,7 +31,7 @@ impl FromStream for TcpStream {
 impl FromStream for UnixStream {
     fn from_stream(stream: Stream) -> Self {
         match stream {
-            Stream::Unix(unix) => unix,
+            Stream::Unix(unix, _) => unix,
             _ => unreachable!("Can not be casted to UnixStream"),
         }
     }
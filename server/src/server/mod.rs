@@ -40,6 +40,7 @@ impl Server {
             worker_threads,
             worker_max_blocking_threads,
             connection_limit,
+            connection_counter,
             listeners,
             factories,
             shutdown_timeout,
@@ -100,6 +101,7 @@ impl Server {
         let worker_handles = (0..worker_threads)
             .map(|idx| {
                 let is_graceful_shutdown = is_graceful_shutdown.clone();
+                let connection_counter = connection_counter.clone();
                 let listeners = listeners.clone();
                 let factories = factories
                     .iter()
@@ -136,6 +138,7 @@ impl Server {
                                         listeners,
                                         services,
                                         connection_limit,
+                                        connection_counter,
                                         shutdown_timeout,
                                         is_graceful_shutdown,
                                     )
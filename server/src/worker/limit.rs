@@ -3,9 +3,36 @@ use std::{
     future::Future,
     pin::Pin,
     rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     task::{Context, Poll, Waker},
 };
 
+/// Thread safe counter of living connections across all worker threads of a server.
+///
+/// Obtained from [Builder::connection_counter](crate::Builder::connection_counter) and
+/// can be cheaply cloned and shared with other threads (e.g. for exporting metrics).
+#[derive(Clone, Default)]
+pub struct ConnectionCounter(Arc<AtomicUsize>);
+
+impl ConnectionCounter {
+    /// Current count of living connections.
+    #[inline]
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn incr(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn decr(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 #[derive(Clone)]
 pub(super) struct Limit {
     limit: usize,
@@ -15,6 +42,7 @@ pub(super) struct Limit {
 struct LimitInner {
     current: Cell<usize>,
     waker: Cell<Option<Waker>>,
+    counter: ConnectionCounter,
 }
 
 pub(crate) struct LimitGuard(Limit);
@@ -28,16 +56,18 @@ impl Drop for LimitGuard {
             }
         }
         self.0.inner.current.set(current - 1);
+        self.0.inner.counter.decr();
     }
 }
 
 impl Limit {
-    pub(super) fn new(limit: usize) -> Self {
+    pub(super) fn new(limit: usize, counter: ConnectionCounter) -> Self {
         Self {
             limit,
             inner: Rc::new(LimitInner {
                 current: Cell::new(0),
                 waker: Cell::new(None),
+                counter,
             }),
         }
     }
@@ -64,6 +94,7 @@ impl Future for LimitReady<'_> {
             Poll::Pending
         } else {
             this.inner.current.set(current + 1);
+            this.inner.counter.incr();
 
             Poll::Ready(LimitGuard(this.clone()))
         }
@@ -78,7 +109,8 @@ mod test {
     async fn counter() {
         tokio::task::LocalSet::new()
             .run_until(async {
-                let limit = Limit::new(2);
+                let counter = ConnectionCounter::default();
+                let limit = Limit::new(2, counter.clone());
 
                 let mut guards = Vec::new();
                 let guard = limit.ready().await;
@@ -87,6 +119,8 @@ mod test {
                 let guard = limit.ready().await;
                 guards.push(guard);
 
+                assert_eq!(counter.get(), 2);
+
                 tokio::task::spawn_local(async move {
                     tokio::time::sleep(std::time::Duration::from_secs(3)).await;
                     drop(guards);
@@ -99,6 +133,7 @@ mod test {
                 let _guard = limit.ready().await;
 
                 assert!(now.elapsed() > std::time::Duration::from_secs(2));
+                assert_eq!(counter.get(), 2);
             })
             .await
     }
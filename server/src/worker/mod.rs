@@ -2,6 +2,8 @@ mod limit;
 mod service;
 mod shutdown;
 
+pub use self::limit::ConnectionCounter;
+
 pub(crate) use self::service::{RcWorkerService, WorkerService};
 
 use std::{
@@ -50,10 +52,11 @@ pub(crate) async fn run(
     listeners: Vec<(String, Arc<Listener>)>,
     services: Vec<(String, RcWorkerService)>,
     connection_limit: usize,
+    connection_counter: ConnectionCounter,
     shutdown_timeout: Duration,
     is_graceful_shutdown: Arc<AtomicBool>,
 ) {
-    let limit = Limit::new(connection_limit);
+    let limit = Limit::new(connection_limit, connection_counter);
 
     let handles = listeners
         .into_iter()
@@ -1,5 +1,5 @@
 use core::{
-    future::Future,
+    future::{ready, Future, Ready},
     ops::{Deref, DerefMut},
 };
 
@@ -46,6 +46,30 @@ impl<Req, Res, Err> Clone for ServiceObject<Req, Res, Err> {
     }
 }
 
+impl<Req, Res, Err> Service<Req> for ServiceObject<Req, Res, Err> {
+    type Response = Res;
+    type Error = Err;
+    type Ready<'f>
+    where
+        Self: 'f,
+    = Ready<Result<(), Self::Error>>;
+    type Future<'f>
+    where
+        Self: 'f,
+    = BoxFuture<'static, Res, Err>;
+
+    // readiness is checked in the object's call future.
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        ready(Ok(()))
+    }
+
+    #[inline]
+    fn call(&self, req: Req) -> Self::Future<'_> {
+        (**self).call(req)
+    }
+}
+
 #[doc(hidden)]
 pub trait _ServiceObject<Req, Res, Err> {
     type Future: Future<Output = Result<Res, Err>>;
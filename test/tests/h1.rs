@@ -7,12 +7,12 @@ use std::{
 
 use xitca_client::Client;
 use xitca_http::{
-    body::ResponseBody,
+    body::{RequestTrailers, ResponseBody, Trailers},
     bytes::{Bytes, BytesMut},
     h1,
     http::{
         header::{self, HeaderValue, CONNECTION},
-        Method, Request, Response,
+        IntoResponse, Method, Request, Response,
     },
 };
 use xitca_service::fn_service;
@@ -215,6 +215,61 @@ async fn h1_keepalive() -> Result<(), Error> {
     Ok(())
 }
 
+#[tokio::test]
+async fn h1_trailers() -> Result<(), Error> {
+    let mut handle = test_h1_server(|| fn_service(handle))?;
+
+    let mut stream = TcpStream::connect(handle.addr())?;
+
+    stream.write_all(
+        b"POST /trailers HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n3\r\nfoo\r\n0\r\nx-check: bar\r\n\r\n",
+    )?;
+
+    let mut res = Vec::new();
+    let mut buf = [0; 128];
+    while !res.ends_with(b"0\r\nx-check: bar\r\n\r\n") {
+        let n = stream.read(&mut buf)?;
+        assert_ne!(n, 0);
+        res.extend_from_slice(&buf[..n]);
+    }
+
+    handle.try_handle()?.stop(true);
+
+    handle.await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn h1_trailers_into_response() -> Result<(), Error> {
+    let mut handle = test_h1_server(|| fn_service(handle))?;
+
+    let mut stream = TcpStream::connect(handle.addr())?;
+
+    stream.write_all(
+        b"POST /into_response HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n3\r\nfoo\r\n0\r\nx-check: bar\r\n\r\n",
+    )?;
+
+    let mut res = Vec::new();
+    let mut buf = [0; 128];
+    while !res.ends_with(b"\r\n\r\n") || !res.windows(3).any(|w| w == b"foo") {
+        let n = stream.read(&mut buf)?;
+        assert_ne!(n, 0);
+        res.extend_from_slice(&buf[..n]);
+    }
+
+    // request trailers must not be sent back with response.
+    let res = String::from_utf8(res).unwrap();
+    assert!(res.ends_with("3\r\nfoo\r\n0\r\n\r\n"));
+    assert!(!res.contains("x-check"));
+
+    handle.try_handle()?.stop(true);
+
+    handle.await?;
+
+    Ok(())
+}
+
 async fn handle(req: Request<h1::RequestBody>) -> Result<Response<ResponseBody>, Error> {
     // Some yield for testing h1 dispatcher's concurrent future handling.
     tokio::task::yield_now().await;
@@ -253,6 +308,40 @@ async fn handle(req: Request<h1::RequestBody>) -> Result<Response<ResponseBody>,
 
             Ok(Response::new(Bytes::new().into()))
         }
+        // echo request body and trailers back.
+        (&Method::POST, "/trailers") => {
+            let (parts, mut body) = req.into_parts();
+
+            let mut bytes = BytesMut::new();
+            while let Some(chunk) = body.next().await {
+                bytes.extend_from_slice(&chunk?);
+            }
+
+            let trailers = parts.extensions.get::<RequestTrailers>().unwrap().take().unwrap();
+
+            let res_trailers = Trailers::new();
+            res_trailers.set(trailers);
+
+            let body = futures_util::stream::once(async move { Ok::<_, xitca_http::BodyError>(bytes.freeze()) });
+            let mut res = Response::new(ResponseBody::stream(Box::pin(body) as _));
+            res.extensions_mut().insert(res_trailers);
+
+            Ok(res)
+        }
+        // read request body to end and respond with request's extensions.
+        (&Method::POST, "/into_response") => {
+            let (parts, mut body) = req.into_parts();
+
+            let mut bytes = BytesMut::new();
+            while let Some(chunk) = body.next().await {
+                bytes.extend_from_slice(&chunk?);
+            }
+
+            let body = futures_util::stream::once(async move { Ok::<_, xitca_http::BodyError>(bytes.freeze()) });
+            let body = ResponseBody::stream(Box::pin(body) as _);
+
+            Ok(Request::from_parts(parts, ()).into_response(body))
+        }
         (&Method::GET, "/close_connection") => {
             let mut res = Response::new(Bytes::new().into());
             res.headers_mut().insert(CONNECTION, HeaderValue::from_static("close"));
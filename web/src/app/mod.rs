@@ -3,18 +3,25 @@ mod entry;
 use std::future::Future;
 
 use futures_core::future::LocalBoxFuture;
-use xitca_http::{http::Request, RequestBody, ResponseError};
+use xitca_http::{
+    http::{Request, Response, StatusCode},
+    RequestBody, ResponseError,
+};
 use xitca_service::{Service, ServiceFactory, ServiceFactoryExt, Transform};
 
-use crate::request::WebRequest;
+use crate::{
+    request::WebRequest,
+    service::{Mount, NotFoundService},
+};
 
 // App keeps a similar API to xitca-web::App. But in real it can be much simpler.
 
 type StateFactory<State> = Box<dyn Fn() -> LocalBoxFuture<'static, State>>;
 
-pub struct App<SF = StateFactory<()>, F = ()> {
+pub struct App<SF = StateFactory<()>, F = NotFoundService, D = NotFoundService> {
     state_factory: SF,
     pub factory: F,
+    default: Option<D>,
 }
 
 impl Default for App {
@@ -27,7 +34,8 @@ impl App {
     pub fn new() -> App {
         Self {
             state_factory: Box::new(|| Box::pin(async {})),
-            factory: (),
+            factory: NotFoundService,
+            default: None,
         }
     }
 }
@@ -68,20 +76,53 @@ impl App {
     {
         App {
             state_factory,
-            factory: (),
+            factory: NotFoundService,
+            default: None,
         }
     }
 }
 
-impl<SF, F> App<SF, F> {
-    pub fn service<F1>(self, factory: F1) -> App<SF, F1> {
+impl<SF, F, D> App<SF, F, D> {
+    pub fn service<F1>(self, factory: F1) -> App<SF, F1, D> {
         App {
             state_factory: self.state_factory,
             factory,
+            default: self.default,
         }
     }
 
-    pub fn middleware<Req, T>(self, transform: T) -> App<SF, impl ServiceFactory<Req>>
+    /// Set a fallback service factory for requests App's service does not match.
+    ///
+    /// When App's service fails with an error reporting the request is not matched (see
+    /// [ResponseError::is_not_matched]) the request is passed to default service instead of
+    /// converting the error to response. e.g. serving an SPA's `index.html` or proxying to a
+    /// legacy backend. Responses of App's service (including `404 Not Found`) are not affected.
+    pub fn default_service<D1>(self, factory: D1) -> App<SF, F, D1> {
+        App {
+            state_factory: self.state_factory,
+            factory: self.factory,
+            default: Some(factory),
+        }
+    }
+
+    /// Mount an App with it's own state and middlewares under given path prefix.
+    ///
+    /// Requests with path under the prefix are dispatched to mounted App with the prefix
+    /// stripped from their path. e.g. `/admin/users` would be seen as `/users` by App mounted
+    /// to `/admin`. The rest of requests are dispatched to current App's service.
+    pub fn mount<SF1, F1, D1>(
+        self,
+        prefix: &'static str,
+        app: App<SF1, F1, D1>,
+    ) -> App<SF, Mount<F, App<SF1, F1, D1>>, D> {
+        App {
+            state_factory: self.state_factory,
+            factory: Mount::new(prefix, self.factory, app),
+            default: self.default,
+        }
+    }
+
+    pub fn middleware<Req, T>(self, transform: T) -> App<SF, impl ServiceFactory<Req>, D>
     where
         F: ServiceFactory<Req>,
         T: Transform<F::Service, Req>,
@@ -90,55 +131,80 @@ impl<SF, F> App<SF, F> {
         App {
             state_factory: self.state_factory,
             factory: self.factory.transform(transform),
+            default: self.default,
         }
     }
 }
 
-impl<SF, Fut, F, S, Res, Err, Cfg, IntErr> ServiceFactory<Request<RequestBody>> for App<SF, F>
+impl<SF, Fut, F, S, D, DS, ResB, Err, DErr, Cfg, IntErr, DIntErr> ServiceFactory<Request<RequestBody>> for App<SF, F, D>
 where
     SF: Fn() -> Fut,
     Fut: Future + 'static,
     F: for<'rb, 'r> ServiceFactory<
         &'rb mut WebRequest<'r, Fut::Output>,
         Service = S,
-        Response = Res,
+        Response = Response<ResB>,
         Error = Err,
         Config = Cfg,
         InitError = IntErr,
     >,
-    S: for<'rb, 'r> Service<&'rb mut WebRequest<'r, Fut::Output>, Response = Res, Error = Err> + 'static,
-    Err: for<'r> ResponseError<WebRequest<'r, Fut::Output>, Res>,
+    S: for<'rb, 'r> Service<&'rb mut WebRequest<'r, Fut::Output>, Response = Response<ResB>, Error = Err> + 'static,
+    Err: for<'r> ResponseError<WebRequest<'r, Fut::Output>, Response<ResB>>,
+    D: for<'rb, 'r> ServiceFactory<
+        &'rb mut WebRequest<'r, Fut::Output>,
+        Service = DS,
+        Response = Response<ResB>,
+        Error = DErr,
+        Config = Cfg,
+        InitError = DIntErr,
+    >,
+    DS: for<'rb, 'r> Service<&'rb mut WebRequest<'r, Fut::Output>, Response = Response<ResB>, Error = DErr> + 'static,
+    DErr: for<'r> ResponseError<WebRequest<'r, Fut::Output>, Response<ResB>>,
+    IntErr: From<DIntErr>,
+    Cfg: Clone,
 {
-    type Response = Res;
+    type Response = Response<ResB>;
     type Error = Err;
     type Config = Cfg;
-    type Service = AppService<Fut::Output, S>;
+    type Service = AppService<Fut::Output, S, DS>;
     type InitError = IntErr;
     type Future = impl Future<Output = Result<Self::Service, Self::InitError>>;
 
     fn new_service(&self, cfg: Self::Config) -> Self::Future {
         let state = (&self.state_factory)();
+        let default = self.default.as_ref().map(|default| default.new_service(cfg.clone()));
         let service = self.factory.new_service(cfg);
         async {
             let state = state.await;
             let service = service.await?;
-            Ok(AppService { service, state })
+            let default = match default {
+                Some(default) => Some(default.await?),
+                None => None,
+            };
+            Ok(AppService {
+                service,
+                default,
+                state,
+            })
         }
     }
 }
 
-pub struct AppService<State, S> {
+pub struct AppService<State, S, D> {
     state: State,
     service: S,
+    default: Option<D>,
 }
 
-impl<State, S, Res, Err> Service<Request<RequestBody>> for AppService<State, S>
+impl<State, S, D, ResB, Err, DErr> Service<Request<RequestBody>> for AppService<State, S, D>
 where
     State: 'static,
-    S: for<'r, 's> Service<&'r mut WebRequest<'s, State>, Response = Res, Error = Err> + 'static,
-    Err: for<'r> ResponseError<WebRequest<'r, State>, Res>,
+    S: for<'r, 's> Service<&'r mut WebRequest<'s, State>, Response = Response<ResB>, Error = Err> + 'static,
+    Err: for<'r> ResponseError<WebRequest<'r, State>, Response<ResB>>,
+    D: for<'r, 's> Service<&'r mut WebRequest<'s, State>, Response = Response<ResB>, Error = DErr> + 'static,
+    DErr: for<'r> ResponseError<WebRequest<'r, State>, Response<ResB>>,
 {
-    type Response = Res;
+    type Response = Response<ResB>;
     type Error = Err;
     type Ready<'f> = impl Future<Output = Result<(), Self::Error>>;
     type Future<'f> = impl Future<Output = Result<Self::Response, Self::Error>>;
@@ -151,13 +217,22 @@ where
     fn call(&self, req: Request<RequestBody>) -> Self::Future<'_> {
         async move {
             let mut req = WebRequest::new(req, &self.state);
-            let res = self
-                .service
-                .call(&mut req)
-                .await
-                .unwrap_or_else(|ref mut e| ResponseError::response_error(e, &mut req));
 
-            Ok(res)
+            match self.service.call(&mut req).await {
+                Ok(res) => Ok(res),
+                Err(mut e) => {
+                    let not_matched = <Err as ResponseError<WebRequest<'_, State>, Response<ResB>>>::is_not_matched(&e);
+
+                    match self.default {
+                        // pass request not matched by service to default service.
+                        Some(ref default) if not_matched => Ok(default
+                            .call(&mut req)
+                            .await
+                            .unwrap_or_else(|ref mut e| ResponseError::response_error(e, &mut req))),
+                        _ => Ok(ResponseError::response_error(&mut e, &mut req)),
+                    }
+                }
+            }
         }
     }
 }
@@ -166,6 +241,9 @@ where
 mod test {
     use super::*;
 
+    use xitca_http::http::{HeaderValue, Uri};
+    use xitca_service::fn_service;
+
     use crate::response::{ResponseBody, WebResponse};
 
     struct TestFactory;
@@ -199,7 +277,12 @@ mod test {
             async move {
                 assert_eq!(req.state(), "state");
 
-                Ok(WebResponse::new(ResponseBody::None))
+                let mut res = WebResponse::new(ResponseBody::None);
+                if req.request_ref().uri().path() != "/" {
+                    *res.status_mut() = StatusCode::NOT_FOUND;
+                }
+
+                Ok(res)
             }
         }
     }
@@ -216,6 +299,69 @@ mod test {
         let _ = service.call(req).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_default_service() {
+        #[derive(Debug)]
+        struct NotMatched;
+
+        impl<Req> ResponseError<Req, WebResponse> for NotMatched {
+            fn response_error(&mut self, _: &mut Req) -> WebResponse {
+                let mut res = WebResponse::new(ResponseBody::None);
+                *res.status_mut() = StatusCode::NOT_FOUND;
+                res
+            }
+
+            fn is_not_matched(&self) -> bool {
+                true
+            }
+        }
+
+        async fn service(req: &mut WebRequest<'_, String>) -> Result<WebResponse, NotMatched> {
+            let mut res = WebResponse::new(ResponseBody::None);
+            match req.request_ref().uri().path() {
+                "/" => Ok(res),
+                "/missing" => {
+                    *res.status_mut() = StatusCode::NOT_FOUND;
+                    Ok(res)
+                }
+                _ => Err(NotMatched),
+            }
+        }
+
+        async fn default(req: &mut WebRequest<'_, String>) -> Result<WebResponse, ()> {
+            assert_eq!(req.state(), "state");
+
+            let mut res = WebResponse::new(ResponseBody::None);
+            res.headers_mut()
+                .insert("x-default", HeaderValue::from_static("index.html"));
+            Ok(res)
+        }
+
+        let state = String::from("state");
+        let app = App::with_current_thread_state(state)
+            .default_service(fn_service(default))
+            .service(fn_service(service));
+
+        let service = app.new_service(()).await.ok().unwrap();
+
+        let res = service.call(Request::default()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get("x-default").is_none());
+
+        // 404 responded by service is not passed to default service.
+        let mut req = Request::default();
+        *req.uri_mut() = Uri::from_static("/missing");
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.headers().get("x-default").is_none());
+
+        let mut req = Request::default();
+        *req.uri_mut() = Uri::from_static("/spa/page");
+        let res = service.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get("x-default").unwrap(), "index.html");
+    }
+
     // #[tokio::test]
     // async fn test_handler() {
     //     use crate::extract::State;
@@ -1 +1,41 @@
+use std::{error, fmt};
+
+use xitca_http::{
+    http::{
+        header::{HeaderValue, CONTENT_TYPE},
+        Response, StatusCode,
+    },
+    ResponseBody, ResponseError,
+};
+
+use crate::request::WebRequest;
+
 pub use xitca_http::BodyError;
+
+/// Error type of extractors provided by this crate.
+#[derive(Debug)]
+pub enum ExtractError {
+    /// Type with given name is not found in request's extensions. It's usually caused by the
+    /// middleware inserting it is not applied.
+    MissingExtension(&'static str),
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::MissingExtension(name) => write!(f, "{} is not found in request extensions", name),
+        }
+    }
+}
+
+impl error::Error for ExtractError {}
+
+impl<'r, D, B> ResponseError<WebRequest<'r, D>, Response<ResponseBody<B>>> for ExtractError {
+    fn response_error(&mut self, _: &mut WebRequest<'r, D>) -> Response<ResponseBody<B>> {
+        Response::builder()
+            .status(<Self as ResponseError<WebRequest<'r, D>, Response<ResponseBody<B>>>>::status_code())
+            .header(CONTENT_TYPE, HeaderValue::from_static("text/plain; charset=utf-8"))
+            .body(self.to_string().into())
+            .unwrap()
+    }
+}
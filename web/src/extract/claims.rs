@@ -0,0 +1,41 @@
+use std::{future::Future, ops::Deref};
+
+use crate::{error::ExtractError, request::WebRequest};
+
+use super::{from_extensions, FromRequest};
+
+/// Decoded claims of a verified JSON Web Token.
+///
+/// It's inserted into request's extensions by [Jwt](crate::middleware::jwt::Jwt) middleware.
+/// Extracting it without the middleware would result in an error.
+#[derive(Debug, Clone)]
+pub struct Claims<T>(pub T);
+
+impl<T> Claims<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Claims<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a, D, T> FromRequest<'a, D> for Claims<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Config = ();
+    type Error = ExtractError;
+
+    type Future = impl Future<Output = Result<Self, Self::Error>> + 'a;
+
+    fn from_request(req: &'a WebRequest<'_, D>) -> Self::Future {
+        let claims = from_extensions::<Self, D>(req);
+        async move { claims }
+    }
+}
@@ -0,0 +1,40 @@
+use std::future::Future;
+
+pub use xitca_http::{util::middleware::ForwardedInfo, ConnectionInfo};
+
+use crate::{error::ExtractError, request::WebRequest};
+
+use super::{from_extensions, FromRequest};
+
+// ConnectionInfo is inserted into request's extensions by http dispatchers.
+// When it's absent (e.g. request is not received from a connection) addresses are reported as None.
+impl<'a, D> FromRequest<'a, D> for ConnectionInfo {
+    type Config = ();
+    type Error = ();
+
+    type Future = impl Future<Output = Result<Self, Self::Error>> + 'a;
+
+    fn from_request(req: &'a WebRequest<'_, D>) -> Self::Future {
+        let info = req
+            .request_ref()
+            .extensions()
+            .get::<ConnectionInfo>()
+            .cloned()
+            .unwrap_or_default();
+        async move { Ok(info) }
+    }
+}
+
+// ForwardedInfo is inserted into request's extensions by TrustedProxy middleware.
+// Extracting it without the middleware would result in an error.
+impl<'a, D> FromRequest<'a, D> for ForwardedInfo {
+    type Config = ();
+    type Error = ExtractError;
+
+    type Future = impl Future<Output = Result<Self, Self::Error>> + 'a;
+
+    fn from_request(req: &'a WebRequest<'_, D>) -> Self::Future {
+        let info = from_extensions::<Self, D>(req);
+        async move { info }
+    }
+}
@@ -0,0 +1,39 @@
+use std::{future::Future, ops::Deref};
+
+use crate::{error::ExtractError, request::WebRequest};
+
+use super::{from_extensions, FromRequest};
+
+/// CSRF token of current request.
+///
+/// It's inserted into request's extensions by [Csrf](crate::middleware::csrf::Csrf) middleware
+/// and meant to be rendered into forms or pages for submitting with unsafe requests.
+/// Extracting it without the middleware would result in an error.
+#[derive(Debug, Clone)]
+pub struct CsrfToken(pub(crate) String);
+
+impl CsrfToken {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for CsrfToken {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a, D> FromRequest<'a, D> for CsrfToken {
+    type Config = ();
+    type Error = ExtractError;
+
+    type Future = impl Future<Output = Result<Self, Self::Error>> + 'a;
+
+    fn from_request(req: &'a WebRequest<'_, D>) -> Self::Future {
+        let token = from_extensions::<Self, D>(req);
+        async move { token }
+    }
+}
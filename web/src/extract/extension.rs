@@ -0,0 +1,105 @@
+use std::{cell::Ref, future::Future, ops::Deref};
+
+use crate::{error::ExtractError, request::WebRequest};
+
+use super::{from_extensions, missing_extension, FromRequest};
+
+/// Extract a clone of type from request's extensions.
+///
+/// Extracting a type that is not inserted into request's extensions (usually by a middleware)
+/// would result in [ExtractError::MissingExtension] which is logged and responded with
+/// `500 Internal Server Error`.
+#[derive(Debug, Clone)]
+pub struct Extension<T>(pub T);
+
+impl<T> Extension<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Extension<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a, D, T> FromRequest<'a, D> for Extension<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Config = ();
+    type Error = ExtractError;
+
+    type Future = impl Future<Output = Result<Self, Self::Error>> + 'a;
+
+    fn from_request(req: &'a WebRequest<'_, D>) -> Self::Future {
+        let ext = from_extensions::<T, D>(req).map(Extension);
+        async move { ext }
+    }
+}
+
+/// Extract a reference of type from request's extensions without cloning it.
+///
+/// The reference keeps an immutable borrow of [WebRequest]'s inner request. Calling
+/// [WebRequest::request_ref_mut] while it's alive would panic.
+///
+/// Extracting a type that is not inserted into request's extensions (usually by a middleware)
+/// would result in [ExtractError::MissingExtension] which is logged and responded with
+/// `500 Internal Server Error`.
+pub struct ExtensionRef<'a, T>(Ref<'a, T>);
+
+impl<T> Deref for ExtensionRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a, D, T> FromRequest<'a, D> for ExtensionRef<'a, T>
+where
+    T: Send + Sync + 'static,
+{
+    type Config = ();
+    type Error = ExtractError;
+
+    type Future = impl Future<Output = Result<Self, Self::Error>> + 'a;
+
+    fn from_request(req: &'a WebRequest<'_, D>) -> Self::Future {
+        let req = req.request_ref();
+        let ext = if req.extensions().get::<T>().is_some() {
+            Ok(ExtensionRef(Ref::map(req, |req| req.extensions().get::<T>().unwrap())))
+        } else {
+            Err(missing_extension::<T>())
+        };
+        async move { ext }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn extension() {
+        let mut req = WebRequest::with_state(&());
+        req.request_mut().extensions_mut().insert(String::from("tenant"));
+
+        let ext = Extension::<String>::from_request(&req).await.unwrap();
+        assert_eq!(ext.as_str(), "tenant");
+
+        {
+            let ext = ExtensionRef::<String>::from_request(&req).await.unwrap();
+            assert_eq!(ext.as_str(), "tenant");
+        }
+
+        assert!(matches!(
+            Extension::<u32>::from_request(&req).await,
+            Err(ExtractError::MissingExtension("u32"))
+        ));
+        assert!(ExtensionRef::<u32>::from_request(&req).await.is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use std::{future::Future, ops::Deref};
+
+use crate::{error::ExtractError, request::WebRequest};
+
+use super::{from_extensions, FromRequest};
+
+/// Identity of authenticated client.
+///
+/// It's inserted into request's extensions by authentication middlewares in
+/// [middleware::auth](crate::middleware::auth) and can be extracted by handlers.
+/// Extracting it without the middleware would result in an error.
+#[derive(Debug, Clone)]
+pub struct Identity<T>(pub T);
+
+impl<T> Identity<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Identity<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a, D, T> FromRequest<'a, D> for Identity<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Config = ();
+    type Error = ExtractError;
+
+    type Future = impl Future<Output = Result<Self, Self::Error>> + 'a;
+
+    fn from_request(req: &'a WebRequest<'_, D>) -> Self::Future {
+        let identity = from_extensions::<Self, D>(req);
+        async move { identity }
+    }
+}
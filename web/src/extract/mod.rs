@@ -1,10 +1,26 @@
+#[cfg(feature = "jwt")]
+mod claims;
+mod connection_info;
+#[cfg(feature = "csrf")]
+mod csrf;
+mod extension;
+mod identity;
 mod state;
+mod url_for;
 
-pub use self::state::State;
+#[cfg(feature = "jwt")]
+pub use self::claims::Claims;
+pub use self::connection_info::{ConnectionInfo, ForwardedInfo};
+#[cfg(feature = "csrf")]
+pub use self::csrf::CsrfToken;
+pub use self::extension::{Extension, ExtensionRef};
+pub use self::identity::Identity;
+pub use self::state::{FromRef, State};
+pub use self::url_for::{UrlFor, UrlForError};
 
 use std::future::Future;
 
-use crate::request::WebRequest;
+use crate::{error::ExtractError, request::WebRequest};
 
 /// Trait implemented by types that can be extracted from request.
 ///
@@ -37,6 +53,27 @@ where
     }
 }
 
+// clone type inserted into request's extensions by middlewares. shared by extractors of those types.
+fn from_extensions<T, D>(req: &WebRequest<'_, D>) -> Result<T, ExtractError>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    req.request_ref()
+        .extensions()
+        .get::<T>()
+        .cloned()
+        .ok_or_else(missing_extension::<T>)
+}
+
+fn missing_extension<T>() -> ExtractError {
+    let name = std::any::type_name::<T>();
+    tracing::error!(
+        "{} is not found in request extensions. Is the middleware inserting it applied?",
+        name
+    );
+    ExtractError::MissingExtension(name)
+}
+
 macro_rules! tuple_from_req ({ $($T:ident)* } => {
     impl<'a, State, Err, $($T),+> FromRequest<'a, State> for ($($T,)+)
     where
@@ -5,7 +5,8 @@ use crate::request::WebRequest;
 use super::FromRequest;
 
 /// App state extractor.
-/// S type must be the same with the type passed to App::with_xxx_state(<S>).
+/// S type must be the same with the type passed to App::with_xxx_state(<S>) or a type
+/// that can be borrowed from it through [FromRef] trait.
 pub struct State<'a, S>(&'a S);
 
 impl<S> Deref for State<'_, S> {
@@ -16,9 +17,40 @@ impl<S> Deref for State<'_, S> {
     }
 }
 
-impl<'a, S> FromRequest<'a, S> for State<'a, S>
+/// Projection from App state to a part of it.
+///
+/// Enables [State] extractor to extract a field of composite App state so handlers do not have
+/// to depend on the full App state type.
+///
+/// # Example:
+/// ```rust
+/// # use xitca_web::extract::FromRef;
+/// struct AppState {
+///     db: String,
+///     cache: Vec<u8>,
+/// }
+///
+/// impl FromRef<AppState> for String {
+///     fn from_ref(state: &AppState) -> &Self {
+///         &state.db
+///     }
+/// }
+/// ```
+pub trait FromRef<S> {
+    fn from_ref(state: &S) -> &Self;
+}
+
+impl<S> FromRef<S> for S {
+    #[inline]
+    fn from_ref(state: &S) -> &Self {
+        state
+    }
+}
+
+impl<'a, S, T> FromRequest<'a, S> for State<'a, T>
 where
-    S: Clone + 'static,
+    S: 'static,
+    T: FromRef<S> + 'static,
 {
     type Config = ();
     type Error = ();
@@ -26,6 +58,33 @@ where
     type Future = impl Future<Output = Result<Self, Self::Error>> + 'a;
 
     fn from_request(req: &'a WebRequest<'_, S>) -> Self::Future {
-        async move { Ok(State(req.state)) }
+        async move { Ok(State(T::from_ref(req.state))) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct AppState {
+        db: String,
+    }
+
+    impl FromRef<AppState> for String {
+        fn from_ref(state: &AppState) -> &Self {
+            &state.db
+        }
+    }
+
+    #[tokio::test]
+    async fn project() {
+        let state = AppState { db: String::from("db") };
+        let req = WebRequest::with_state(&state);
+
+        let db = State::<String>::from_request(&req).await.unwrap();
+        assert_eq!(db.as_str(), "db");
+
+        let state = State::<AppState>::from_request(&req).await.unwrap();
+        assert_eq!(state.db, "db");
     }
 }
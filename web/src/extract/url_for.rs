@@ -0,0 +1,26 @@
+use std::future::Future;
+
+pub use xitca_http::util::service::{UrlFor, UrlForError};
+
+use crate::{error::ExtractError, request::WebRequest, service::MountPrefix};
+
+use super::{from_extensions, FromRequest};
+
+// UrlFor is inserted into request's extensions by Router when it has named routes.
+// Extracting it without named routes would result in an error.
+// When the request is dispatched to a mounted App the mount prefix is prepended to generated urls.
+impl<'a, D> FromRequest<'a, D> for UrlFor {
+    type Config = ();
+    type Error = ExtractError;
+
+    type Future = impl Future<Output = Result<Self, Self::Error>> + 'a;
+
+    fn from_request(req: &'a WebRequest<'_, D>) -> Self::Future {
+        let url_for =
+            from_extensions::<Self, D>(req).map(|url_for| match req.request_ref().extensions().get::<MountPrefix>() {
+                Some(prefix) => url_for.prefix(prefix.as_str()),
+                None => url_for,
+            });
+        async move { url_for }
+    }
+}
@@ -2,11 +2,12 @@
 #![feature(generic_associated_types, type_alias_impl_trait)]
 
 mod app;
-mod extract;
 mod guard;
 mod server;
 
 pub mod error;
+pub mod extract;
+pub mod middleware;
 pub mod request;
 pub mod response;
 pub mod service;
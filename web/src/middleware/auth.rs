@@ -0,0 +1,387 @@
+//! Authentication middlewares.
+//!
+//! Every middleware extracts credentials from request headers and pass them to a user provided
+//! async validator. When validator resolves to `Some(T)` the value is inserted into request's
+//! extensions and can be extracted with [Identity](crate::extract::Identity). Otherwise a
+//! `401 Unauthorized` response with `WWW-Authenticate` challenge is returned.
+//!
+//! # Example:
+//! ```rust,ignore
+//! use xitca_web::{extract::Identity, middleware::auth::{Auth, BearerToken}};
+//!
+//! #[derive(Clone)]
+//! struct User(String);
+//!
+//! let app = App::new()
+//!     .service(HandlerService::new(index))
+//!     .middleware(Auth::bearer(|token: BearerToken| async move {
+//!         (token.as_str() == "secret").then(|| User(String::from("admin")))
+//!     }));
+//!
+//! async fn index(user: Identity<User>) -> WebResponse { .. }
+//! ```
+
+use std::future::Future;
+
+use xitca_http::http::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE},
+    StatusCode,
+};
+use xitca_service::{Service, Transform};
+
+use crate::{dev::bytes::Bytes, extract::Identity, request::WebRequest, response::WebResponse};
+
+/// Trait for extracting credentials from request headers and generating challenge on failure.
+pub trait AuthScheme: Clone {
+    /// Credentials passed to validator.
+    type Credentials;
+
+    /// Extract credentials from request headers. Return None when credentials are absent or
+    /// malformed.
+    fn credentials(&self, headers: &HeaderMap) -> Option<Self::Credentials>;
+
+    /// Value of `WWW-Authenticate` header when authentication failed.
+    fn challenge(&self) -> HeaderValue;
+}
+
+/// Credentials of HTTP Basic authentication scheme. (RFC 7617)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicCredentials {
+    pub user_id: String,
+    pub password: String,
+}
+
+/// Token of HTTP Bearer authentication scheme. (RFC 6750)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BearerToken(pub String);
+
+impl BearerToken {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Key of API-key authentication.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKey(pub String);
+
+impl ApiKey {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// HTTP Basic authentication scheme.
+#[derive(Clone)]
+pub struct Basic {
+    challenge: HeaderValue,
+}
+
+impl AuthScheme for Basic {
+    type Credentials = BasicCredentials;
+
+    fn credentials(&self, headers: &HeaderMap) -> Option<Self::Credentials> {
+        let value = authorization(headers, "Basic")?;
+        let decoded = base64::decode(value).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (user_id, password) = decoded.split_once(':')?;
+
+        Some(BasicCredentials {
+            user_id: user_id.to_owned(),
+            password: password.to_owned(),
+        })
+    }
+
+    fn challenge(&self) -> HeaderValue {
+        self.challenge.clone()
+    }
+}
+
+/// HTTP Bearer authentication scheme.
+#[derive(Clone)]
+pub struct Bearer {
+    challenge: HeaderValue,
+}
+
+impl AuthScheme for Bearer {
+    type Credentials = BearerToken;
+
+    fn credentials(&self, headers: &HeaderMap) -> Option<Self::Credentials> {
+        authorization(headers, "Bearer").map(|token| BearerToken(token.to_owned()))
+    }
+
+    fn challenge(&self) -> HeaderValue {
+        self.challenge.clone()
+    }
+}
+
+/// API-key authentication scheme where key is passed in a custom header.
+#[derive(Clone)]
+pub struct ApiKeyHeader {
+    header: HeaderName,
+    challenge: HeaderValue,
+}
+
+impl AuthScheme for ApiKeyHeader {
+    type Credentials = ApiKey;
+
+    fn credentials(&self, headers: &HeaderMap) -> Option<Self::Credentials> {
+        headers
+            .get(&self.header)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty())
+            .map(|v| ApiKey(v.to_owned()))
+    }
+
+    fn challenge(&self) -> HeaderValue {
+        self.challenge.clone()
+    }
+}
+
+// get the credentials part of authorization header with given scheme.
+// scheme is matched case insensitively.
+pub(super) fn authorization<'a>(headers: &'a HeaderMap, scheme: &str) -> Option<&'a str> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (s, credentials) = value.split_once(' ')?;
+    let credentials = credentials.trim();
+    (s.eq_ignore_ascii_case(scheme) && !credentials.is_empty()).then(|| credentials)
+}
+
+fn challenge(scheme: &str, realm: &str) -> HeaderValue {
+    HeaderValue::from_str(&format!("{} realm=\"{}\"", scheme, realm)).expect("Realm must be a valid header value")
+}
+
+/// A factory for authentication service.
+#[derive(Clone)]
+pub struct Auth<Sc, V> {
+    scheme: Sc,
+    validator: V,
+}
+
+impl<V> Auth<Basic, V> {
+    /// Construct HTTP Basic authentication with given validator.
+    pub fn basic(validator: V) -> Self {
+        Self::basic_with_realm("xitca", validator)
+    }
+
+    /// Construct HTTP Basic authentication with given realm and validator.
+    ///
+    /// # Panics:
+    /// When realm is not a valid header value.
+    pub fn basic_with_realm(realm: &str, validator: V) -> Self {
+        let mut value = challenge("Basic", realm).as_bytes().to_vec();
+        value.extend_from_slice(b", charset=\"UTF-8\"");
+
+        Self {
+            scheme: Basic {
+                challenge: HeaderValue::from_bytes(&value).unwrap(),
+            },
+            validator,
+        }
+    }
+}
+
+impl<V> Auth<Bearer, V> {
+    /// Construct HTTP Bearer authentication with given validator.
+    pub fn bearer(validator: V) -> Self {
+        Self::bearer_with_realm("xitca", validator)
+    }
+
+    /// Construct HTTP Bearer authentication with given realm and validator.
+    ///
+    /// # Panics:
+    /// When realm is not a valid header value.
+    pub fn bearer_with_realm(realm: &str, validator: V) -> Self {
+        Self {
+            scheme: Bearer {
+                challenge: challenge("Bearer", realm),
+            },
+            validator,
+        }
+    }
+}
+
+impl<V> Auth<ApiKeyHeader, V> {
+    /// Construct API-key authentication where key is read from given header.
+    pub fn api_key(header: HeaderName, validator: V) -> Self {
+        let challenge = HeaderValue::from_str(&format!("ApiKey header=\"{}\"", header.as_str())).unwrap();
+        Self {
+            scheme: ApiKeyHeader { header, challenge },
+            validator,
+        }
+    }
+}
+
+impl<Sc, V> Auth<Sc, V> {
+    /// Construct authentication middleware with custom [AuthScheme].
+    pub fn with_scheme(scheme: Sc, validator: V) -> Self {
+        Self { scheme, validator }
+    }
+}
+
+impl<'r, 's, S, State, Sc, V, Fut, T> Transform<S, &'r mut WebRequest<'s, State>> for Auth<Sc, V>
+where
+    S: Service<&'r mut WebRequest<'s, State>, Response = WebResponse>,
+    Sc: AuthScheme,
+    V: Fn(Sc::Credentials) -> Fut + Clone,
+    Fut: Future<Output = Option<T>>,
+    T: Send + Sync + 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Transform = AuthService<S, Sc, V>;
+    type InitError = ();
+    type Future = impl Future<Output = Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let scheme = self.scheme.clone();
+        let validator = self.validator.clone();
+        async move {
+            Ok(AuthService {
+                service,
+                scheme,
+                validator,
+            })
+        }
+    }
+}
+
+pub struct AuthService<S, Sc, V> {
+    service: S,
+    scheme: Sc,
+    validator: V,
+}
+
+impl<'r, 's, S, State, Sc, V, Fut, T> Service<&'r mut WebRequest<'s, State>> for AuthService<S, Sc, V>
+where
+    S: Service<&'r mut WebRequest<'s, State>, Response = WebResponse>,
+    Sc: AuthScheme,
+    V: Fn(Sc::Credentials) -> Fut,
+    Fut: Future<Output = Option<T>>,
+    T: Send + Sync + 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Ready<'f>
+    where
+        Self: 'f,
+    = S::Ready<'f>;
+    type Future<'f>
+    where
+        Self: 'f,
+    = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        self.service.ready()
+    }
+
+    fn call(&self, req: &'r mut WebRequest<'s, State>) -> Self::Future<'_> {
+        async move {
+            let credentials = self.scheme.credentials(req.request_ref().headers());
+
+            let identity = match credentials {
+                Some(credentials) => (self.validator)(credentials).await,
+                None => None,
+            };
+
+            match identity {
+                Some(identity) => {
+                    req.request_mut().extensions_mut().insert(Identity(identity));
+                    self.service.call(req).await
+                }
+                None => {
+                    let mut res = req.as_response(Bytes::new());
+                    *res.status_mut() = StatusCode::UNAUTHORIZED;
+                    res.headers_mut().insert(WWW_AUTHENTICATE, self.scheme.challenge());
+                    Ok(res)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basic_credentials() {
+        let auth = Auth::basic(|_: BasicCredentials| async { Some(()) });
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="),
+        );
+
+        let credentials = auth.scheme.credentials(&headers).unwrap();
+        assert_eq!(credentials.user_id, "Aladdin");
+        assert_eq!(credentials.password, "open sesame");
+
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Bearer QWxhZGRpbjpvcGVuIHNlc2FtZQ=="),
+        );
+        assert!(auth.scheme.credentials(&headers).is_none());
+
+        assert_eq!(
+            auth.scheme.challenge(),
+            HeaderValue::from_static("Basic realm=\"xitca\", charset=\"UTF-8\"")
+        );
+    }
+
+    #[test]
+    fn bearer_token() {
+        let auth = Auth::bearer(|_: BearerToken| async { Some(()) });
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("bearer mF_9.B5f-4.1JqM"));
+
+        assert_eq!(auth.scheme.credentials(&headers).unwrap().as_str(), "mF_9.B5f-4.1JqM");
+    }
+
+    #[tokio::test]
+    async fn bearer_middleware() {
+        use xitca_service::ServiceFactory;
+
+        use crate::{response::ResponseBody, service::HandlerService};
+
+        async fn handler(user: Identity<String>) -> WebResponse {
+            WebResponse::new(ResponseBody::from(user.into_inner()))
+        }
+
+        let service = HandlerService::new(handler).new_service(()).await.ok().unwrap();
+        async fn validate(token: BearerToken) -> Option<String> {
+            (token.as_str() == "secret").then(|| String::from("admin"))
+        }
+
+        let auth = Auth::bearer(validate);
+        let service = Transform::<_, &mut WebRequest<'_, ()>>::new_transform(&auth, service)
+            .await
+            .unwrap();
+
+        let mut req = WebRequest::with_state(&());
+        let res = service.call(&mut req).await.ok().unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(res.headers().get(WWW_AUTHENTICATE).unwrap(), "Bearer realm=\"xitca\"");
+
+        let mut req = WebRequest::with_state(&());
+        req.request_mut()
+            .headers_mut()
+            .insert(AUTHORIZATION, HeaderValue::from_static("Bearer guess"));
+        let res = service.call(&mut req).await.ok().unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let mut req = WebRequest::with_state(&());
+        req.request_mut()
+            .headers_mut()
+            .insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        let res = service.call(&mut req).await.ok().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        match res.body() {
+            ResponseBody::Bytes { bytes } => assert_eq!(bytes.as_ref(), b"admin"),
+            _ => panic!("handler response body must be bytes"),
+        }
+    }
+}
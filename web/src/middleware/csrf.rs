@@ -0,0 +1,395 @@
+//! Cross-site request forgery protection middleware.
+//!
+//! The middleware implements double-submit cookie pattern:
+//!
+//! - Every request gets a token from a cookie. A new token is generated and set to response
+//!   cookie when request does not carry one. Handlers can extract it with
+//!   [CsrfToken](crate::extract::CsrfToken) and render it into forms or pages.
+//! - Requests with unsafe methods (everything except GET, HEAD, OPTIONS and TRACE) must submit
+//!   the same token in a request header or an `application/x-www-form-urlencoded` form field.
+//! - Requests with unsafe methods must also have `Origin` or `Referer` header matching allowed
+//!   origins (or request's host when no origin is configured) when the headers are present.
+//!
+//! Requests failed the verification are rejected with `403 Forbidden` response.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::stream::Stream;
+use rand::RngCore;
+use xitca_http::{
+    bytes::{Bytes, BytesMut},
+    error::BodyError,
+    http::{
+        header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, COOKIE, HOST, ORIGIN, REFERER, SET_COOKIE},
+        Method, Request, StatusCode,
+    },
+    RequestBody,
+};
+use xitca_service::{Service, Transform};
+
+use crate::{extract::CsrfToken, request::WebRequest, response::WebResponse};
+
+/// A factory for CSRF protection service.
+#[derive(Clone)]
+pub struct Csrf {
+    cookie_name: &'static str,
+    header_name: HeaderName,
+    form_field: &'static str,
+    allowed_origins: Vec<String>,
+    secure: bool,
+    body_limit: usize,
+}
+
+impl Default for Csrf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Csrf {
+    pub fn new() -> Self {
+        Self {
+            cookie_name: "csrf_token",
+            header_name: HeaderName::from_static("x-csrf-token"),
+            form_field: "csrf_token",
+            allowed_origins: Vec::new(),
+            secure: true,
+            body_limit: 64 * 1024,
+        }
+    }
+
+    /// Change name of the cookie storing token.
+    ///
+    /// Default to `csrf_token`.
+    pub fn cookie_name(mut self, name: &'static str) -> Self {
+        self.cookie_name = name;
+        self
+    }
+
+    /// Change name of request header carrying submitted token.
+    ///
+    /// Default to `x-csrf-token`.
+    pub fn header_name(mut self, name: HeaderName) -> Self {
+        self.header_name = name;
+        self
+    }
+
+    /// Change name of form field carrying submitted token.
+    ///
+    /// Default to `csrf_token`.
+    pub fn form_field(mut self, name: &'static str) -> Self {
+        self.form_field = name;
+        self
+    }
+
+    /// Add an allowed origin in the form of `scheme://host[:port]`.
+    ///
+    /// When no origin is added `Origin` and `Referer` headers are checked against request's
+    /// `Host` header or the authority of request's uri when `Host` is absent. (HTTP/2 and HTTP/3)
+    pub fn allowed_origin(mut self, origin: impl Into<String>) -> Self {
+        self.allowed_origins.push(origin.into());
+        self
+    }
+
+    /// Set `Secure` attribute of token cookie.
+    ///
+    /// Default to true.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Max size of form body that would be buffered for looking up token field.
+    ///
+    /// Default to 64KiB.
+    pub fn body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = limit;
+        self
+    }
+
+    fn cookie(&self, headers: &HeaderMap) -> Option<String> {
+        headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(name, _)| *name == self.cookie_name)
+            .map(|(_, value)| value.to_owned())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn set_cookie(&self, token: &str) -> HeaderValue {
+        let mut value = format!("{}={}; Path=/; SameSite=Strict", self.cookie_name, token);
+        if self.secure {
+            value.push_str("; Secure");
+        }
+        HeaderValue::from_str(&value).unwrap()
+    }
+
+    // check Origin and fallback to Referer. absent of both headers is allowed and left to
+    // token verification.
+    fn check_origin<B>(&self, req: &Request<B>) -> bool {
+        let headers = req.headers();
+
+        let origin = match headers.get(ORIGIN) {
+            Some(origin) => match origin.to_str() {
+                Ok(origin) => origin,
+                Err(_) => return false,
+            },
+            None => match headers.get(REFERER) {
+                Some(referer) => match referer.to_str().ok().and_then(origin_of) {
+                    Some(origin) => origin,
+                    None => return false,
+                },
+                None => return true,
+            },
+        };
+
+        if self.allowed_origins.is_empty() {
+            let host = match headers.get(HOST) {
+                Some(host) => host.to_str().ok(),
+                None => req.uri().authority().map(|a| a.as_str()),
+            };
+            matches!((host_of(origin), host), (Some(origin), Some(host)) if origin.eq_ignore_ascii_case(host))
+        } else {
+            self.allowed_origins.iter().any(|o| o.eq_ignore_ascii_case(origin))
+        }
+    }
+}
+
+// scheme://host[:port] part of an url.
+fn origin_of(url: &str) -> Option<&str> {
+    let idx = url.find("://")? + 3;
+    let end = url[idx..].find('/').map(|i| i + idx).unwrap_or(url.len());
+    Some(&url[..end])
+}
+
+// host[:port] part of an origin.
+fn host_of(origin: &str) -> Option<&str> {
+    origin.split_once("://").map(|(_, host)| host).filter(|h| !h.is_empty())
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE)
+}
+
+impl<'r, 's, S, State> Transform<S, &'r mut WebRequest<'s, State>> for Csrf
+where
+    S: Service<&'r mut WebRequest<'s, State>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Transform = CsrfService<S>;
+    type InitError = ();
+    type Future = impl Future<Output = Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let config = self.clone();
+        async move { Ok(CsrfService { service, config }) }
+    }
+}
+
+pub struct CsrfService<S> {
+    service: S,
+    config: Csrf,
+}
+
+impl<S> CsrfService<S> {
+    // look up submitted token from header and then form body.
+    async fn submitted_token<State>(&self, req: &mut WebRequest<'_, State>) -> Option<String> {
+        let req = req.request_mut();
+
+        if let Some(token) = req.headers().get(&self.config.header_name) {
+            return token.to_str().ok().map(str::to_owned);
+        }
+
+        let is_form = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.starts_with("application/x-www-form-urlencoded"))
+            .unwrap_or(false);
+
+        if !is_form {
+            return None;
+        }
+
+        let mut body = std::mem::take(req.body_mut());
+        let mut buf = BytesMut::new();
+
+        while let Some(chunk) = Next(&mut body).await {
+            let chunk = chunk.ok()?;
+            if buf.len() + chunk.len() > self.config.body_limit {
+                return None;
+            }
+            buf.extend_from_slice(&chunk);
+        }
+
+        let bytes = buf.freeze();
+
+        let token = form_field(&bytes, self.config.form_field);
+
+        // put collected body back for inner service.
+        *req.body_mut() = RequestBody::Bytes(Some(bytes));
+
+        token
+    }
+}
+
+fn form_field(body: &Bytes, field: &str) -> Option<String> {
+    std::str::from_utf8(body)
+        .ok()?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == field)
+        .map(|(_, value)| value.to_owned())
+}
+
+struct Next<'a>(&'a mut RequestBody);
+
+impl Future for Next<'_> {
+    type Output = Option<Result<Bytes, BodyError>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.get_mut().0).poll_next(cx)
+    }
+}
+
+impl<'r, 's, S, State> Service<&'r mut WebRequest<'s, State>> for CsrfService<S>
+where
+    S: Service<&'r mut WebRequest<'s, State>, Response = WebResponse>,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Ready<'f>
+    where
+        Self: 'f,
+    = S::Ready<'f>;
+    type Future<'f>
+    where
+        Self: 'f,
+    = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        self.service.ready()
+    }
+
+    fn call(&self, req: &'r mut WebRequest<'s, State>) -> Self::Future<'_> {
+        async move {
+            let cookie = self.config.cookie(req.request_ref().headers());
+
+            if !is_safe_method(req.request_ref().method()) {
+                let origin = self.config.check_origin(&*req.request_ref());
+
+                let verified = origin
+                    && match cookie {
+                        Some(ref cookie) => match self.submitted_token(req).await {
+                            Some(token) => constant_time_eq(cookie.as_bytes(), token.as_bytes()),
+                            None => false,
+                        },
+                        None => false,
+                    };
+
+                if !verified {
+                    let mut res = req.as_response(Bytes::new());
+                    *res.status_mut() = StatusCode::FORBIDDEN;
+                    return Ok(res);
+                }
+            }
+
+            let (token, is_new) = match cookie {
+                Some(token) => (token, false),
+                None => (generate_token(), true),
+            };
+
+            req.request_mut().extensions_mut().insert(CsrfToken(token.clone()));
+
+            let mut res = self.service.call(req).await?;
+
+            if is_new {
+                res.headers_mut().append(SET_COOKIE, self.config.set_cookie(&token));
+            }
+
+            Ok(res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn origin() {
+        let csrf = Csrf::new();
+
+        let mut req = Request::new(());
+        req.headers_mut().insert(HOST, HeaderValue::from_static("example.com"));
+        assert!(csrf.check_origin(&req));
+
+        req.headers_mut()
+            .insert(REFERER, HeaderValue::from_static("https://example.com/admin/form"));
+        assert!(csrf.check_origin(&req));
+
+        req.headers_mut()
+            .insert(ORIGIN, HeaderValue::from_static("https://evil.com"));
+        assert!(!csrf.check_origin(&req));
+
+        let csrf = csrf.allowed_origin("https://evil.com");
+        assert!(csrf.check_origin(&req));
+
+        req.headers_mut().insert(ORIGIN, HeaderValue::from_static("null"));
+        assert!(!csrf.check_origin(&req));
+    }
+
+    #[test]
+    fn origin_h2() {
+        use xitca_http::http::{Uri, Version};
+
+        let csrf = Csrf::new();
+
+        // HTTP/2 request carries host in :authority pseudo header instead of Host header.
+        let mut req = Request::new(());
+        *req.version_mut() = Version::HTTP_2;
+        *req.method_mut() = Method::POST;
+        *req.uri_mut() = Uri::from_static("https://example.com/admin/form");
+        req.headers_mut()
+            .insert(ORIGIN, HeaderValue::from_static("https://example.com"));
+        assert!(csrf.check_origin(&req));
+
+        req.headers_mut()
+            .insert(ORIGIN, HeaderValue::from_static("https://evil.com"));
+        assert!(!csrf.check_origin(&req));
+    }
+
+    #[test]
+    fn cookie_and_form() {
+        let csrf = Csrf::new();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_static("foo=bar; csrf_token=abc"));
+        assert_eq!(csrf.cookie(&headers).as_deref(), Some("abc"));
+
+        let body = Bytes::from_static(b"name=foo&csrf_token=abc");
+        assert_eq!(form_field(&body, "csrf_token").as_deref(), Some("abc"));
+
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+    }
+}
@@ -0,0 +1,593 @@
+//! JSON Web Token validation middleware.
+//!
+//! Token is read from `Authorization: Bearer <token>` header and verified with keys from a
+//! [Jwks] key set. On success the decoded claims are inserted into request's extensions and
+//! can be extracted with [Claims](crate::extract::Claims).
+//!
+//! # Example:
+//! ```rust,ignore
+//! use xitca_web::{extract::Claims, middleware::jwt::{Algorithm, Jwks, Jwt}};
+//!
+//! #[derive(Clone, serde::Deserialize)]
+//! struct MyClaims {
+//!     sub: String,
+//! }
+//!
+//! // key set fetched from OIDC provider. (requires jwt-remote feature)
+//! let jwks = Jwks::from_url("https://example.com/.well-known/jwks.json");
+//!
+//! let factory = move || {
+//!     App::new()
+//!         .service(HandlerService::new(index))
+//!         .middleware(
+//!             Jwt::<MyClaims>::new(jwks.clone())
+//!                 .algorithms(&[Algorithm::RS256, Algorithm::ES256])
+//!                 .audience(&["my-api"])
+//!                 .issuer(&["https://example.com/"]),
+//!         )
+//! };
+//!
+//! async fn index(claims: Claims<MyClaims>) -> WebResponse { .. }
+//! ```
+
+use std::{
+    error, fmt,
+    future::Future,
+    io,
+    marker::PhantomData,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    DecodingKey, Validation,
+};
+use serde::de::DeserializeOwned;
+use xitca_http::http::{
+    header::{HeaderValue, WWW_AUTHENTICATE},
+    StatusCode,
+};
+use xitca_service::{Service, Transform};
+
+use crate::{dev::bytes::Bytes, extract::Claims, request::WebRequest, response::WebResponse};
+
+use super::auth::authorization;
+
+pub use jsonwebtoken::Algorithm;
+
+/// Error type of loading key set.
+pub enum JwtError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Jwt(jsonwebtoken::errors::Error),
+    #[cfg(feature = "jwt-remote")]
+    Client(xitca_client::error::Error),
+}
+
+impl fmt::Debug for JwtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Io(ref e) => write!(f, "{:?}", e),
+            Self::Json(ref e) => write!(f, "{:?}", e),
+            Self::Jwt(ref e) => write!(f, "{:?}", e),
+            #[cfg(feature = "jwt-remote")]
+            Self::Client(ref e) => write!(f, "{:?}", e),
+        }
+    }
+}
+
+impl fmt::Display for JwtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Io(ref e) => write!(f, "{}", e),
+            Self::Json(ref e) => write!(f, "{}", e),
+            Self::Jwt(ref e) => write!(f, "{}", e),
+            #[cfg(feature = "jwt-remote")]
+            Self::Client(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for JwtError {}
+
+impl From<io::Error> for JwtError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for JwtError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for JwtError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        Self::Jwt(e)
+    }
+}
+
+#[cfg(feature = "jwt-remote")]
+impl From<xitca_client::error::Error> for JwtError {
+    fn from(e: xitca_client::error::Error) -> Self {
+        Self::Client(e)
+    }
+}
+
+/// A set of keys for verifying token signatures.
+///
+/// Key set is thread safe and cheap to clone. It can be constructed outside of server's
+/// service factory closure and shared by all worker threads.
+#[derive(Clone)]
+pub struct Jwks {
+    inner: Arc<JwksInner>,
+}
+
+struct JwksInner {
+    keys: RwLock<Vec<Key>>,
+    #[cfg(feature = "jwt-remote")]
+    remote: Option<remote::Remote>,
+}
+
+struct Key {
+    kid: Option<String>,
+    alg: Option<Algorithm>,
+    key: DecodingKey,
+}
+
+impl Jwks {
+    fn new(keys: Vec<Key>) -> Self {
+        Self {
+            inner: Arc::new(JwksInner {
+                keys: RwLock::new(keys),
+                #[cfg(feature = "jwt-remote")]
+                remote: None,
+            }),
+        }
+    }
+
+    /// Construct key set from a JWKS json document.
+    pub fn from_json(json: &[u8]) -> Result<Self, JwtError> {
+        parse_jwks(json).map(Self::new)
+    }
+
+    /// Construct key set from a local JWKS json file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, JwtError> {
+        let json = std::fs::read(path)?;
+        Self::from_json(&json)
+    }
+
+    /// Construct key set with a single shared secret for HS256/HS384/HS512 algorithms.
+    pub fn from_secret(secret: &[u8]) -> Self {
+        Self::new(vec![Key {
+            kid: None,
+            alg: None,
+            key: DecodingKey::from_secret(secret),
+        }])
+    }
+
+    // find key by kid. when token does not have kid the first key compatible with
+    // algorithm is used.
+    fn find(&self, kid: Option<&str>, alg: Algorithm) -> Option<DecodingKey> {
+        let keys = self.inner.keys.read().unwrap();
+        keys.iter()
+            .filter(|key| key.alg.map(|a| a == alg).unwrap_or(true))
+            .find(|key| match kid {
+                Some(kid) => key.kid.as_deref() == Some(kid),
+                None => true,
+            })
+            .map(|key| key.key.clone())
+    }
+
+    #[cfg(feature = "jwt-remote")]
+    fn is_empty(&self) -> bool {
+        self.inner.keys.read().unwrap().is_empty()
+    }
+
+    fn replace(&self, keys: Vec<Key>) {
+        *self.inner.keys.write().unwrap() = keys;
+    }
+}
+
+fn parse_jwks(json: &[u8]) -> Result<Vec<Key>, JwtError> {
+    let set = serde_json::from_slice::<JwkSet>(json)?;
+    set.keys.iter().map(key_from_jwk).collect()
+}
+
+fn key_from_jwk(jwk: &Jwk) -> Result<Key, JwtError> {
+    Ok(Key {
+        kid: jwk.common.key_id.clone(),
+        alg: jwk.common.algorithm,
+        key: DecodingKey::from_jwk(jwk)?,
+    })
+}
+
+#[cfg(feature = "jwt-remote")]
+mod remote {
+    use std::{
+        sync::{Arc, RwLock},
+        time::{Duration, Instant},
+    };
+
+    use tokio::sync::Mutex;
+    use xitca_client::Client;
+
+    use super::{parse_jwks, Jwks, JwksInner, JwtError};
+
+    pub(super) struct Remote {
+        url: String,
+        min_interval: Duration,
+        // time of last successful refresh. the lock is held while fetching so concurrent
+        // callers wait for the in flight refresh instead of failing.
+        last_refresh: Mutex<Option<Instant>>,
+    }
+
+    impl Jwks {
+        /// Construct key set that is fetched from given url with xitca-client.
+        ///
+        /// Keys are fetched when [Jwt] middleware is constructed and refreshed when a token with
+        /// unknown `kid` is received. Refresh would happen at most once in 60 seconds by default.
+        pub fn from_url(url: impl Into<String>) -> Self {
+            Self::from_url_with_interval(url, Duration::from_secs(60))
+        }
+
+        /// Same as [Jwks::from_url] with custom minimal refresh interval.
+        pub fn from_url_with_interval(url: impl Into<String>, min_interval: Duration) -> Self {
+            Self {
+                inner: Arc::new(JwksInner {
+                    keys: RwLock::new(Vec::new()),
+                    remote: Some(Remote {
+                        url: url.into(),
+                        min_interval,
+                        last_refresh: Mutex::new(None),
+                    }),
+                }),
+            }
+        }
+
+        /// Fetch key set from remote url and replace current keys.
+        ///
+        /// Callers arriving while a refresh is in flight wait for it and share its outcome.
+        ///
+        /// Return Ok(false) when key set is not remote or refresh is skipped because the
+        /// minimal interval has not passed since last successful refresh.
+        pub async fn refresh(&self, client: &Client) -> Result<bool, JwtError> {
+            let remote = match self.inner.remote {
+                Some(ref remote) => remote,
+                None => return Ok(false),
+            };
+
+            let start = Instant::now();
+
+            let mut last = remote.last_refresh.lock().await;
+
+            match *last {
+                // key set is refreshed by other caller while waiting for the lock.
+                Some(last) if last >= start => return Ok(true),
+                Some(last) if start - last < remote.min_interval => return Ok(false),
+                _ => {}
+            }
+
+            let body = client.get(remote.url.as_str())?.send().await?.body().await?;
+            let keys = parse_jwks(&body)?;
+            self.replace(keys);
+
+            *last = Some(Instant::now());
+
+            Ok(true)
+        }
+    }
+}
+
+/// A factory for JWT validation service.
+///
+/// Generic type `C` is the claims type that token payload would be deserialized into.
+pub struct Jwt<C> {
+    jwks: Jwks,
+    validation: Validation,
+    _claims: PhantomData<fn() -> C>,
+}
+
+impl<C> Clone for Jwt<C> {
+    fn clone(&self) -> Self {
+        Self {
+            jwks: self.jwks.clone(),
+            validation: self.validation.clone(),
+            _claims: PhantomData,
+        }
+    }
+}
+
+impl<C> Jwt<C> {
+    /// Construct a new JWT validator with given key set.
+    ///
+    /// By default RS256 algorithm is accepted and `exp` and `nbf` claims are validated.
+    pub fn new(jwks: Jwks) -> Self {
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.validate_nbf = true;
+
+        Self {
+            jwks,
+            validation,
+            _claims: PhantomData,
+        }
+    }
+
+    /// Set accepted signing algorithms.
+    pub fn algorithms(mut self, algorithms: &[Algorithm]) -> Self {
+        self.validation.algorithms = algorithms.to_vec();
+        self
+    }
+
+    /// Set accepted values of `aud` claim.
+    pub fn audience<T: ToString>(mut self, audience: &[T]) -> Self {
+        self.validation.set_audience(audience);
+        self
+    }
+
+    /// Set accepted values of `iss` claim.
+    pub fn issuer<T: ToString>(mut self, issuer: &[T]) -> Self {
+        self.validation.set_issuer(issuer);
+        self
+    }
+
+    /// Set leeway in seconds for validating `exp` and `nbf` claims.
+    pub fn leeway(mut self, secs: u64) -> Self {
+        self.validation.leeway = secs;
+        self
+    }
+}
+
+impl<'r, 's, S, State, C> Transform<S, &'r mut WebRequest<'s, State>> for Jwt<C>
+where
+    S: Service<&'r mut WebRequest<'s, State>, Response = WebResponse>,
+    C: DeserializeOwned + Send + Sync + 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Transform = JwtService<S, C>;
+    type InitError = ();
+    type Future = impl Future<Output = Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let this = self.clone();
+        async move {
+            #[cfg(feature = "jwt-remote")]
+            let client = {
+                let client = xitca_client::Client::new();
+
+                // fetch remote key set ahead of first request.
+                if this.jwks.is_empty() {
+                    if let Err(e) = this.jwks.refresh(&client).await {
+                        tracing::error!("Failed to fetch jwks: {}", e);
+                    }
+                }
+
+                client
+            };
+
+            Ok(JwtService {
+                service,
+                jwks: this.jwks,
+                validation: this.validation,
+                #[cfg(feature = "jwt-remote")]
+                client,
+                _claims: PhantomData,
+            })
+        }
+    }
+}
+
+pub struct JwtService<S, C> {
+    service: S,
+    jwks: Jwks,
+    validation: Validation,
+    #[cfg(feature = "jwt-remote")]
+    client: xitca_client::Client,
+    _claims: PhantomData<fn() -> C>,
+}
+
+impl<S, C> JwtService<S, C>
+where
+    C: DeserializeOwned,
+{
+    async fn verify(&self, token: &str) -> Result<C, &'static str> {
+        let header = decode_header(token).map_err(|_| "malformed token")?;
+
+        if !self.validation.algorithms.contains(&header.alg) {
+            return Err("unsupported algorithm");
+        }
+
+        let key = match self.jwks.find(header.kid.as_deref(), header.alg) {
+            Some(key) => key,
+            #[cfg(feature = "jwt-remote")]
+            None => {
+                // key set may be rotated. try to refresh it.
+                match self.jwks.refresh(&self.client).await {
+                    Ok(true) => self.jwks.find(header.kid.as_deref(), header.alg).ok_or("unknown key")?,
+                    Ok(false) => return Err("unknown key"),
+                    Err(e) => {
+                        tracing::error!("Failed to refresh jwks: {}", e);
+                        return Err("unknown key");
+                    }
+                }
+            }
+            #[cfg(not(feature = "jwt-remote"))]
+            None => return Err("unknown key"),
+        };
+
+        decode::<C>(token, &key, &self.validation)
+            .map(|data| data.claims)
+            .map_err(|e| match *e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => "token expired",
+                jsonwebtoken::errors::ErrorKind::ImmatureSignature => "token not yet valid",
+                jsonwebtoken::errors::ErrorKind::InvalidAudience => "invalid audience",
+                jsonwebtoken::errors::ErrorKind::InvalidIssuer => "invalid issuer",
+                _ => "invalid token",
+            })
+    }
+}
+
+impl<'r, 's, S, State, C> Service<&'r mut WebRequest<'s, State>> for JwtService<S, C>
+where
+    S: Service<&'r mut WebRequest<'s, State>, Response = WebResponse>,
+    C: DeserializeOwned + Send + Sync + 'static,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Ready<'f>
+    where
+        Self: 'f,
+    = S::Ready<'f>;
+    type Future<'f>
+    where
+        Self: 'f,
+    = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        self.service.ready()
+    }
+
+    fn call(&self, req: &'r mut WebRequest<'s, State>) -> Self::Future<'_> {
+        async move {
+            let token = authorization(req.request_ref().headers(), "Bearer").map(str::to_owned);
+
+            let res = match token {
+                Some(token) => self.verify(&token).await.map_err(Some),
+                None => Err(None),
+            };
+
+            match res {
+                Ok(claims) => {
+                    req.request_mut().extensions_mut().insert(Claims(claims));
+                    self.service.call(req).await
+                }
+                Err(desc) => {
+                    let mut res = req.as_response(Bytes::new());
+                    *res.status_mut() = StatusCode::UNAUTHORIZED;
+                    let challenge = match desc {
+                        Some(desc) => HeaderValue::from_str(&format!(
+                            "Bearer error=\"invalid_token\", error_description=\"{}\"",
+                            desc
+                        ))
+                        .unwrap(),
+                        None => HeaderValue::from_static("Bearer"),
+                    };
+                    res.headers_mut().insert(WWW_AUTHENTICATE, challenge);
+                    Ok(res)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn jwks_parse() {
+        let json = br#"{"keys":[
+            {"kty":"oct","kid":"hs","alg":"HS256","k":"c2VjcmV0"},
+            {"kty":"RSA","kid":"rs","alg":"RS256","e":"AQAB","n":"sRJjz2msgWl0ZbDDZyHrRK7Gd1-Ew8lxGRnzwfTrcXgrHoH0S5sLWPoBvPVrNIRgnkddxbNbixeCJhVvCbcq2tm1tgswz8ok8WpVHtu3Ovc-NOw6q2Pji-g5J3q5_HnW38b18Vj8fv-SApHgrvvvGHdrnhSxLPlutfvm14mPa_0hQnVmjgVL-TG9ai4z0SaeVDO-0TbvG6-9LwGGpvLt6rW8Tm8SPWMxBvmpPP_o0DyjOozgrHt-sJG3ux7hbxR1c-BfGhHAG08uhSDsDpPLc2mlW0wKNb6JANCcWJsyzdoEP3ngzO1S5QzhOqi71XqIKmUhCHX8rx1uhb3a4r8RWQ"}
+        ]}"#;
+
+        let jwks = Jwks::from_json(json).ok().unwrap();
+
+        assert!(jwks.find(Some("hs"), Algorithm::HS256).is_some());
+        assert!(jwks.find(Some("rs"), Algorithm::RS256).is_some());
+        assert!(jwks.find(Some("rs"), Algorithm::HS256).is_none());
+        assert!(jwks.find(Some("es"), Algorithm::ES256).is_none());
+        assert!(jwks.find(None, Algorithm::RS256).is_some());
+    }
+
+    fn service(jwt: Jwt<serde_json::Value>) -> JwtService<(), serde_json::Value> {
+        JwtService {
+            service: (),
+            jwks: jwt.jwks,
+            validation: jwt.validation,
+            #[cfg(feature = "jwt-remote")]
+            client: xitca_client::Client::new(),
+            _claims: PhantomData,
+        }
+    }
+
+    const ISS: &str = "https://example.com/";
+
+    fn token(alg: Algorithm, secret: &[u8], exp: u64, nbf: u64, aud: &str, iss: &str) -> String {
+        use jsonwebtoken::{encode, EncodingKey, Header};
+
+        let claims = serde_json::json!({ "sub": "foo", "exp": exp, "nbf": nbf, "aud": aud, "iss": iss });
+        encode(&Header::new(alg), &claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn verify() {
+        let now = jsonwebtoken::get_current_timestamp();
+
+        let jwt = Jwt::new(Jwks::from_secret(b"secret"))
+            .algorithms(&[Algorithm::HS256])
+            .audience(&["api"])
+            .issuer(&[ISS]);
+        let service = service(jwt);
+
+        let valid = token(Algorithm::HS256, b"secret", now + 600, now, "api", ISS);
+        let claims = service.verify(&valid).await.unwrap();
+        assert_eq!(claims["sub"], "foo");
+
+        let cases = [
+            (
+                token(Algorithm::HS256, b"secret", now - 600, now - 1200, "api", ISS),
+                "token expired",
+            ),
+            (
+                token(Algorithm::HS256, b"secret", now + 1200, now + 600, "api", ISS),
+                "token not yet valid",
+            ),
+            (
+                token(Algorithm::HS256, b"secret", now + 600, now, "other", ISS),
+                "invalid audience",
+            ),
+            (
+                token(Algorithm::HS256, b"secret", now + 600, now, "api", "https://evil.com/"),
+                "invalid issuer",
+            ),
+            (
+                token(Algorithm::HS256, b"guess", now + 600, now, "api", ISS),
+                "invalid token",
+            ),
+            (
+                token(Algorithm::HS384, b"secret", now + 600, now, "api", ISS),
+                "unsupported algorithm",
+            ),
+            (String::from("foo.bar"), "malformed token"),
+        ];
+
+        for (token, err) in cases {
+            assert_eq!(service.verify(&token).await.unwrap_err(), err);
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_key() {
+        let json = br#"{"keys":[{"kty":"oct","kid":"hs","alg":"HS256","k":"c2VjcmV0"}]}"#;
+        let jwt = Jwt::new(Jwks::from_json(json).ok().unwrap()).algorithms(&[Algorithm::HS256]);
+        let service = service(jwt);
+
+        let exp = jsonwebtoken::get_current_timestamp() + 600;
+
+        let mut header = jsonwebtoken::Header::new(Algorithm::HS256);
+        header.kid = Some(String::from("hs"));
+        let key = jsonwebtoken::EncodingKey::from_secret(b"secret");
+        let token = jsonwebtoken::encode(&header, &serde_json::json!({ "exp": exp }), &key).unwrap();
+        assert!(service.verify(&token).await.is_ok());
+
+        header.kid = Some(String::from("rotated"));
+        let token = jsonwebtoken::encode(&header, &serde_json::json!({ "exp": exp }), &key).unwrap();
+        assert_eq!(service.verify(&token).await.unwrap_err(), "unknown key");
+    }
+}
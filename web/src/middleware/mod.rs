@@ -0,0 +1,9 @@
+//! Middlewares for [App](crate::App).
+
+pub mod auth;
+
+#[cfg(feature = "csrf")]
+pub mod csrf;
+
+#[cfg(feature = "jwt")]
+pub mod jwt;
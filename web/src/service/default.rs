@@ -8,6 +8,7 @@ use xitca_service::{Service, ServiceFactory};
 
 use crate::response::WebResponse;
 
+/// Default service of [App](crate::App) that responds with `404 Not Found` to every request.
 pub struct NotFoundService;
 
 impl<Req> ServiceFactory<Req> for NotFoundService {
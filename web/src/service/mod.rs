@@ -1,7 +1,12 @@
 mod default;
 mod r#enum;
 mod handler;
+mod mount;
 
+pub use default::NotFoundService;
 pub use handler::HandlerService;
+pub use mount::{Mount, MountService};
+
+pub(crate) use mount::MountPrefix;
 
 // pub use r#enum::EnumService;
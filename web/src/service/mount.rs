@@ -0,0 +1,213 @@
+use std::{borrow::Cow, future::Future};
+
+use xitca_http::{
+    http::{
+        uri::{PathAndQuery, Uri},
+        Request,
+    },
+    RequestBody, ResponseError,
+};
+use xitca_service::{Service, ServiceFactory};
+
+use crate::request::WebRequest;
+
+/// Service factory dispatch requests with path under a prefix to a mounted [App](crate::App)
+/// and the rest to the outer service.
+///
+/// See [App::mount](crate::App::mount) for detail.
+pub struct Mount<F, A> {
+    prefix: &'static str,
+    service: F,
+    app: A,
+}
+
+impl<F, A> Mount<F, A> {
+    pub(crate) fn new(prefix: &'static str, service: F, app: A) -> Self {
+        Self {
+            prefix: prefix.trim_end_matches('/'),
+            service,
+            app,
+        }
+    }
+}
+
+impl<'r, 's, State, F, A> ServiceFactory<&'r mut WebRequest<'s, State>> for Mount<F, A>
+where
+    F: ServiceFactory<&'r mut WebRequest<'s, State>>,
+    F::Config: Clone,
+    F::Error: From<A::Error>,
+    A: ServiceFactory<Request<RequestBody>, Response = F::Response, Config = F::Config, InitError = F::InitError>,
+    A::Error: ResponseError<WebRequest<'s, State>, F::Response>,
+{
+    type Response = F::Response;
+    type Error = F::Error;
+    type Config = F::Config;
+    type Service = MountService<F::Service, A::Service>;
+    type InitError = F::InitError;
+    type Future = impl Future<Output = Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, cfg: Self::Config) -> Self::Future {
+        let prefix = self.prefix;
+        let service = self.service.new_service(cfg.clone());
+        let app = self.app.new_service(cfg);
+
+        async move {
+            let service = service.await?;
+            let app = app.await?;
+            Ok(MountService { prefix, service, app })
+        }
+    }
+}
+
+/// Path prefix of mounted [App](crate::App) a request is dispatched to.
+///
+/// It's inserted into request's extensions by [Mount] and prefixes of nested mounts are
+/// concatenated.
+#[derive(Clone)]
+pub(crate) struct MountPrefix(Cow<'static, str>);
+
+impl MountPrefix {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct MountService<S, A> {
+    prefix: &'static str,
+    service: S,
+    app: A,
+}
+
+impl<'r, 's, State, S, A> Service<&'r mut WebRequest<'s, State>> for MountService<S, A>
+where
+    S: Service<&'r mut WebRequest<'s, State>>,
+    S::Error: From<A::Error>,
+    A: Service<Request<RequestBody>, Response = S::Response>,
+    A::Error: ResponseError<WebRequest<'s, State>, S::Response>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Ready<'f>
+    where
+        Self: 'f,
+    = impl Future<Output = Result<(), Self::Error>>;
+    type Future<'f>
+    where
+        Self: 'f,
+    = impl Future<Output = Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn ready(&self) -> Self::Ready<'_> {
+        async move {
+            self.service.ready().await?;
+            self.app.ready().await.map_err(From::from)
+        }
+    }
+
+    fn call(&self, req: &'r mut WebRequest<'s, State>) -> Self::Future<'_> {
+        async move {
+            let uri = strip_prefix(self.prefix, req.request_ref().uri());
+
+            match uri {
+                Some(uri) => {
+                    // mounted app owns the request and wraps it with it's own state. the outer
+                    // request is left empty so dispatching to mounted app is terminal: error of
+                    // it is converted to response here and never reaches outer App.
+                    let mut http = std::mem::take(req.request_mut());
+                    *http.uri_mut() = uri;
+
+                    let prefix = match http.extensions().get::<MountPrefix>() {
+                        Some(outer) => Cow::Owned(format!("{}{}", outer.as_str(), self.prefix)),
+                        None => Cow::Borrowed(self.prefix),
+                    };
+                    http.extensions_mut().insert(MountPrefix(prefix));
+
+                    Ok(self
+                        .app
+                        .call(http)
+                        .await
+                        .unwrap_or_else(|ref mut e| ResponseError::response_error(e, req)))
+                }
+                None => self.service.call(req).await,
+            }
+        }
+    }
+}
+
+// strip prefix from uri's path on segment boundary. uri's scheme, authority and query are kept.
+fn strip_prefix(prefix: &str, uri: &Uri) -> Option<Uri> {
+    let rest = uri.path().strip_prefix(prefix)?;
+
+    let path = match rest {
+        "" => "/",
+        rest if rest.starts_with('/') => rest,
+        _ => return None,
+    };
+
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_owned(),
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(PathAndQuery::from_maybe_shared(path_and_query).ok()?);
+    Uri::from_parts(parts).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use xitca_http::http::HeaderValue;
+    use xitca_service::fn_service;
+
+    use crate::{
+        response::{ResponseBody, WebResponse},
+        service::NotFoundService,
+    };
+
+    use super::*;
+
+    #[test]
+    fn prefix() {
+        let uri = Uri::from_static("/admin/users?page=1");
+        assert_eq!(strip_prefix("/admin", &uri).unwrap(), "/users?page=1");
+
+        let uri = Uri::from_static("/admin");
+        assert_eq!(strip_prefix("/admin", &uri).unwrap(), "/");
+
+        let uri = Uri::from_static("/administrator");
+        assert!(strip_prefix("/admin", &uri).is_none());
+
+        let uri = Uri::from_static("/users");
+        assert_eq!(strip_prefix("", &uri).unwrap(), "/users");
+    }
+
+    #[tokio::test]
+    async fn nested_prefix() {
+        let app = fn_service(|req: Request<RequestBody>| async move {
+            let prefix = req.extensions().get::<MountPrefix>().unwrap().as_str();
+            let mut res = WebResponse::new(ResponseBody::None);
+            res.headers_mut()
+                .insert("x-prefix", HeaderValue::from_str(prefix).unwrap());
+            Ok::<_, ()>(res)
+        });
+
+        let service = MountService {
+            prefix: "/v1",
+            service: NotFoundService,
+            app,
+        };
+
+        let mut req = WebRequest::with_state(&());
+        *req.request_mut().uri_mut() = Uri::from_static("/v1/users");
+        let res = service.call(&mut req).await.unwrap();
+        assert_eq!(res.headers().get("x-prefix").unwrap(), "/v1");
+
+        let mut req = WebRequest::with_state(&());
+        *req.request_mut().uri_mut() = Uri::from_static("/v1/users");
+        req.request_mut()
+            .extensions_mut()
+            .insert(MountPrefix(Cow::Borrowed("/api")));
+        let res = service.call(&mut req).await.unwrap();
+        assert_eq!(res.headers().get("x-prefix").unwrap(), "/api/v1");
+    }
+}